@@ -0,0 +1,398 @@
+//! Continuous cluster-invariant auditing
+//!
+//! Borrows the auditor pattern from Pacemaker's CTS: each `ClusterAudit`
+//! asserts exactly one cross-component invariant on its own schedule,
+//! independent of the others, so a slow or disabled check never delays the
+//! rest. A failing audit emits `TelemetryEvent::AuditFailed` and logs at
+//! `warn`; a passing audit stays quiet. Meant to run alongside whatever
+//! long-lived bootstrap/monitoring loop a binary already has - it only reads
+//! cluster state, it never mutates anything.
+
+use crate::telemetry::{Telemetry, TelemetryEvent};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// One invariant, checked independently on its own interval.
+pub trait ClusterAudit: Send + 'static {
+    /// Stable name reported in `TelemetryEvent::AuditFailed.check` and logs.
+    fn name(&self) -> &'static str;
+
+    /// How long to wait between checks.
+    fn interval(&self) -> Duration;
+
+    /// `Ok(())` if the invariant holds, `Err(detail)` otherwise.
+    ///
+    /// Declared as `-> impl Future<...> + Send` rather than `async fn` so the
+    /// returned future has an explicit `Send` bound: `spawn_audits` awaits
+    /// this generically inside `tokio::spawn(run_one(...))`, which requires
+    /// the whole async fn's future to be `Send`, and a bare `async fn` in a
+    /// trait doesn't guarantee that for an arbitrary implementor.
+    fn check(&self) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+/// Drives `audit` forever on its own interval, reporting `AuditFailed` only
+/// on the transition into failure (so a sustained outage doesn't spam
+/// telemetry every tick) and logging recovery once.
+async fn run_one<A: ClusterAudit>(audit: A, telemetry: Telemetry) {
+    let mut ticker = tokio::time::interval(audit.interval());
+    let mut was_failing = false;
+
+    loop {
+        ticker.tick().await;
+
+        match audit.check().await {
+            Ok(()) => {
+                if was_failing {
+                    tracing::info!(audit = audit.name(), "cluster audit recovered");
+                }
+                was_failing = false;
+            }
+            Err(detail) => {
+                warn!(audit = audit.name(), detail = %detail, "cluster audit failed");
+                telemetry.send(TelemetryEvent::AuditFailed {
+                    check: audit.name().to_string(),
+                    detail,
+                });
+                was_failing = true;
+            }
+        }
+    }
+}
+
+/// Which audits are active and how often each one runs, read from the
+/// environment so an operator can disable an expensive check (e.g. the
+/// replication-lag audit, which dials every node) without a redeploy.
+pub struct AuditConfig {
+    pub etcd_quorum: Option<Duration>,
+    pub patroni_single_primary: Option<Duration>,
+    pub replica_count: Option<Duration>,
+    pub replication_lag: Option<Duration>,
+    pub replication_lag_threshold_bytes: u64,
+    pub haproxy_backends: Option<Duration>,
+}
+
+fn audit_interval(enabled_var: &str, interval_var: &str, default_secs: u64) -> Option<Duration> {
+    let enabled = std::env::var(enabled_var).map(|v| v.to_lowercase() == "true").unwrap_or(true);
+    if !enabled {
+        return None;
+    }
+    let secs = std::env::var(interval_var).ok().and_then(|v| v.parse().ok()).unwrap_or(default_secs);
+    Some(Duration::from_secs(secs))
+}
+
+impl AuditConfig {
+    pub fn from_env() -> Self {
+        Self {
+            etcd_quorum: audit_interval("AUDIT_ETCD_QUORUM_ENABLED", "AUDIT_ETCD_QUORUM_INTERVAL_SECS", 10),
+            patroni_single_primary: audit_interval(
+                "AUDIT_PATRONI_SINGLE_PRIMARY_ENABLED",
+                "AUDIT_PATRONI_SINGLE_PRIMARY_INTERVAL_SECS",
+                10,
+            ),
+            replica_count: audit_interval("AUDIT_REPLICA_COUNT_ENABLED", "AUDIT_REPLICA_COUNT_INTERVAL_SECS", 15),
+            replication_lag: audit_interval("AUDIT_REPLICATION_LAG_ENABLED", "AUDIT_REPLICATION_LAG_INTERVAL_SECS", 15),
+            replication_lag_threshold_bytes: std::env::var("AUDIT_REPLICATION_LAG_THRESHOLD_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100 * 1024 * 1024),
+            haproxy_backends: audit_interval("AUDIT_HAPROXY_BACKENDS_ENABLED", "AUDIT_HAPROXY_BACKENDS_INTERVAL_SECS", 10),
+        }
+    }
+}
+
+/// A node's host and Patroni REST API port - the minimum needed to probe
+/// `/primary`, `/replica`, `/health`, and `/patroni`.
+#[derive(Clone)]
+pub struct AuditNode {
+    pub host: String,
+    pub patroni_port: String,
+}
+
+impl AuditNode {
+    fn url(&self, path: &str) -> String {
+        format!("http://{}:{}{}", self.host, self.patroni_port, path)
+    }
+}
+
+struct EtcdQuorumAudit {
+    client: crate::etcd::EtcdClient,
+    endpoints: Vec<String>,
+    interval: Duration,
+}
+
+impl ClusterAudit for EtcdQuorumAudit {
+    fn name(&self) -> &'static str {
+        "etcd_quorum"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let Some(endpoint) = self.client.first_healthy(&self.endpoints).await else {
+            return Err("no healthy etcd endpoint reachable".to_string());
+        };
+
+        let members = self
+            .client
+            .member_list_via(&endpoint)
+            .await
+            .map_err(|e| format!("failed to list members via {}: {}", endpoint, e))?;
+
+        let voters = members.iter().filter(|m| !m.is_learner).count();
+        let mut healthy_voters = 0;
+        for member in &members {
+            if member.is_learner {
+                continue;
+            }
+            if let Some(client_url) = member.client_urls.first() {
+                if self.client.endpoint_health(client_url).await.is_healthy() {
+                    healthy_voters += 1;
+                }
+            }
+        }
+
+        if healthy_voters * 2 <= voters {
+            return Err(format!("only {}/{} voting members healthy, no quorum", healthy_voters, voters));
+        }
+
+        Ok(())
+    }
+}
+
+struct PatroniSinglePrimaryAudit {
+    nodes: Vec<AuditNode>,
+    http: reqwest::Client,
+    interval: Duration,
+}
+
+impl ClusterAudit for PatroniSinglePrimaryAudit {
+    fn name(&self) -> &'static str {
+        "patroni_single_primary"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let mut primaries = Vec::new();
+        for node in &self.nodes {
+            if let Ok(resp) = self.http.get(node.url("/primary")).send().await {
+                if resp.status().is_success() {
+                    primaries.push(node.host.clone());
+                }
+            }
+        }
+
+        match primaries.len() {
+            1 => Ok(()),
+            0 => Err("no node reports itself as primary".to_string()),
+            _ => Err(format!("multiple nodes report primary: {:?}", primaries)),
+        }
+    }
+}
+
+struct ReplicaCountAudit {
+    nodes: Vec<AuditNode>,
+    http: reqwest::Client,
+    interval: Duration,
+}
+
+impl ClusterAudit for ReplicaCountAudit {
+    fn name(&self) -> &'static str {
+        "replica_count"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let mut healthy = 0;
+        for node in &self.nodes {
+            if let Ok(resp) = self.http.get(node.url("/health")).send().await {
+                if resp.status().is_success() {
+                    healthy += 1;
+                }
+            }
+        }
+
+        if healthy < self.nodes.len() {
+            return Err(format!("{}/{} nodes reachable", healthy, self.nodes.len()));
+        }
+        Ok(())
+    }
+}
+
+/// One entry of Patroni's `/patroni` `replication` array, just the fields
+/// this audit needs.
+#[derive(Debug, serde::Deserialize)]
+struct ReplicationStatus {
+    #[serde(default)]
+    sync_state: String,
+    lag: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct PatroniStatusResponse {
+    #[serde(default)]
+    replication: Vec<ReplicationStatus>,
+}
+
+struct ReplicationLagAudit {
+    nodes: Vec<AuditNode>,
+    http: reqwest::Client,
+    threshold_bytes: u64,
+    interval: Duration,
+}
+
+impl ClusterAudit for ReplicationLagAudit {
+    fn name(&self) -> &'static str {
+        "replication_lag"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        // Only the primary's `/patroni` response carries a `replication`
+        // array with per-replica lag; querying every node and taking
+        // whichever answers with a non-empty array avoids needing to know
+        // which one is currently primary.
+        for node in &self.nodes {
+            let Ok(resp) = self.http.get(node.url("/patroni")).send().await else {
+                continue;
+            };
+            let Ok(status) = resp.json::<PatroniStatusResponse>().await else {
+                continue;
+            };
+            if status.replication.is_empty() {
+                continue;
+            }
+
+            for replica in &status.replication {
+                if let Some(lag) = replica.lag {
+                    if lag > self.threshold_bytes {
+                        return Err(format!(
+                            "replica (sync_state={}) lag {} bytes exceeds threshold {} bytes",
+                            replica.sync_state, lag, self.threshold_bytes
+                        ));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        Err("no node returned a replication status".to_string())
+    }
+}
+
+struct HaproxyBackendsAudit {
+    stats_url: String,
+    backends: Vec<&'static str>,
+    http: reqwest::Client,
+    interval: Duration,
+}
+
+impl ClusterAudit for HaproxyBackendsAudit {
+    fn name(&self) -> &'static str {
+        "haproxy_backends"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let body = self
+            .http
+            .get(&self.stats_url)
+            .send()
+            .await
+            .map_err(|e| format!("failed to reach HAProxy stats: {}", e))?
+            .text()
+            .await
+            .map_err(|e| format!("failed to read HAProxy stats: {}", e))?;
+
+        // HAProxy CSV format: pxname,svname,...,status (col 17), skipping
+        // the synthetic "BACKEND" summary row.
+        for backend in &self.backends {
+            let has_up_server = body.lines().any(|line| {
+                let parts: Vec<&str> = line.split(',').collect();
+                parts.len() > 17 && parts[0] == *backend && parts[1] != "BACKEND" && parts[17] == "UP"
+            });
+            if !has_up_server {
+                return Err(format!("backend {} has no reachable (UP) server", backend));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Spawn every audit enabled in `config` as its own task, driven on its own
+/// interval. `etcd_endpoints` empty disables the etcd quorum audit
+/// regardless of `config.etcd_quorum`, since there's nothing to check.
+pub fn spawn_audits(
+    config: AuditConfig,
+    telemetry: Telemetry,
+    nodes: Vec<AuditNode>,
+    etcd_endpoints: Vec<String>,
+    haproxy_stats_url: String,
+) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .expect("building a plain timeout-only client never fails");
+
+    if let Some(interval) = config.etcd_quorum {
+        if !etcd_endpoints.is_empty() {
+            let audit = EtcdQuorumAudit {
+                client: crate::etcd::EtcdClient::new(etcd_endpoints.clone()),
+                endpoints: etcd_endpoints,
+                interval,
+            };
+            tokio::spawn(run_one(audit, telemetry.clone()));
+        }
+    }
+
+    if let Some(interval) = config.patroni_single_primary {
+        if !nodes.is_empty() {
+            let audit = PatroniSinglePrimaryAudit { nodes: nodes.clone(), http: http.clone(), interval };
+            tokio::spawn(run_one(audit, telemetry.clone()));
+        }
+    }
+
+    if let Some(interval) = config.replica_count {
+        if !nodes.is_empty() {
+            let audit = ReplicaCountAudit { nodes: nodes.clone(), http: http.clone(), interval };
+            tokio::spawn(run_one(audit, telemetry.clone()));
+        }
+    }
+
+    if let Some(interval) = config.replication_lag {
+        if !nodes.is_empty() {
+            let audit = ReplicationLagAudit {
+                nodes: nodes.clone(),
+                http: http.clone(),
+                threshold_bytes: config.replication_lag_threshold_bytes,
+                interval,
+            };
+            tokio::spawn(run_one(audit, telemetry.clone()));
+        }
+    }
+
+    if let Some(interval) = config.haproxy_backends {
+        let audit = HaproxyBackendsAudit {
+            stats_url: haproxy_stats_url,
+            backends: vec!["postgresql_primary_backend", "postgresql_replicas_backend"],
+            http,
+            interval,
+        };
+        tokio::spawn(run_one(audit, telemetry));
+    }
+}
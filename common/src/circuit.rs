@@ -0,0 +1,187 @@
+//! Circuit breaker for the command-execution layer
+//!
+//! A per-command-name state machine that trips after repeated failures so a
+//! flapping backend (etcd/Patroni/openssl being briefly unreachable) doesn't
+//! keep forking processes that are doomed to fail.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Circuit breaker state, following the classic closed/open/half-open machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls pass through normally.
+    Closed,
+    /// Calls are rejected immediately until `cooldown` elapses.
+    Open,
+    /// A single trial call is allowed; success closes the breaker, failure
+    /// re-opens it.
+    HalfOpen,
+}
+
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            trial_in_flight: false,
+        }
+    }
+}
+
+/// Registry of circuit breakers keyed by command name.
+///
+/// Shared (e.g. behind an `Arc`) across callers of `run_with_policy` so all
+/// invocations of the same command name see the same breaker state.
+pub struct CircuitBreakerRegistry {
+    breakers: Mutex<HashMap<String, Breaker>>,
+    trip_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreakerRegistry {
+    /// `trip_threshold` consecutive failures opens the breaker; after
+    /// `cooldown` it moves to half-open and allows one trial call.
+    pub fn new(trip_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            breakers: Mutex::new(HashMap::new()),
+            trip_threshold,
+            cooldown,
+        }
+    }
+
+    /// Current state for a command, for telemetry/status reporting.
+    pub fn state(&self, cmd: &str) -> CircuitState {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(cmd.to_string()).or_insert_with(Breaker::new);
+        Self::transition_if_cooled_down(breaker, self.cooldown);
+        breaker.state
+    }
+
+    fn transition_if_cooled_down(breaker: &mut Breaker, cooldown: Duration) {
+        if breaker.state == CircuitState::Open {
+            if let Some(opened_at) = breaker.opened_at {
+                if opened_at.elapsed() >= cooldown {
+                    breaker.state = CircuitState::HalfOpen;
+                    breaker.trial_in_flight = false;
+                }
+            }
+        }
+    }
+
+    /// Called before attempting a command. Returns `false` (and the caller
+    /// must not spawn the process) when the breaker is open, or when it's
+    /// half-open and a trial call is already in flight.
+    pub(crate) fn allow(&self, cmd: &str) -> bool {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(cmd.to_string()).or_insert_with(Breaker::new);
+        Self::transition_if_cooled_down(breaker, self.cooldown);
+
+        match breaker.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                if breaker.trial_in_flight {
+                    false
+                } else {
+                    breaker.trial_in_flight = true;
+                    true
+                }
+            }
+        }
+    }
+
+    pub(crate) fn record_success(&self, cmd: &str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(cmd.to_string()).or_insert_with(Breaker::new);
+        if breaker.state != CircuitState::Closed {
+            info!(cmd, "circuit breaker closing after successful trial");
+        }
+        breaker.state = CircuitState::Closed;
+        breaker.consecutive_failures = 0;
+        breaker.opened_at = None;
+        breaker.trial_in_flight = false;
+    }
+
+    pub(crate) fn record_failure(&self, cmd: &str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(cmd.to_string()).or_insert_with(Breaker::new);
+
+        breaker.consecutive_failures += 1;
+        breaker.trial_in_flight = false;
+
+        if breaker.state == CircuitState::HalfOpen
+            || breaker.consecutive_failures >= self.trip_threshold
+        {
+            if breaker.state != CircuitState::Open {
+                warn!(
+                    cmd,
+                    failures = breaker.consecutive_failures,
+                    "circuit breaker tripped open"
+                );
+            }
+            breaker.state = CircuitState::Open;
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Error returned by `run_with_policy` when the breaker rejects the call
+/// without even attempting it.
+#[derive(Debug, thiserror::Error)]
+#[error("circuit open for {cmd}, not attempting call")]
+pub struct CircuitOpen {
+    pub cmd: String,
+}
+
+/// Exponential backoff with full jitter for `run_with_policy`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub multiplier: f64,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(200),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            max_attempts: 5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given attempt number (0-indexed), with full jitter:
+    /// a uniform random value between 0 and the exponential cap.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let capped = exp.min(self.max_delay.as_secs_f64());
+        let jittered = capped * rand_fraction();
+        Duration::from_secs_f64(jittered)
+    }
+}
+
+/// Minimal dependency-free `[0, 1)` float, good enough for jitter (not for
+/// cryptographic use).
+fn rand_fraction() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 1_000_000) as f64 / 1_000_000.0
+}
@@ -2,10 +2,12 @@
 //!
 //! Provides consistent command execution with proper error handling and logging.
 
+use crate::circuit::{CircuitBreakerRegistry, CircuitOpen, RetryPolicy};
 use anyhow::{anyhow, Context, Result};
 use std::process::Stdio;
 use tokio::process::Command;
-use tracing::{debug, instrument};
+use tokio::time::sleep;
+use tracing::{debug, instrument, warn};
 
 /// Result of a command execution.
 #[derive(Debug)]
@@ -58,6 +60,53 @@ pub async fn run_checked(cmd: &str, args: &[&str]) -> Result<String> {
     }
 }
 
+/// Run a command through a retry policy and a per-command circuit breaker.
+///
+/// Retries on failure with exponential backoff and full jitter up to
+/// `policy.max_attempts`. The breaker in `registry` is consulted before each
+/// attempt: if it's open, this returns `CircuitOpen` immediately without
+/// spawning the process; a successful call closes the breaker, a failure
+/// counts toward tripping it open.
+///
+/// # Example
+/// ```ignore
+/// let registry = CircuitBreakerRegistry::new(5, Duration::from_secs(30));
+/// let out = run_with_policy("etcdctl", &["endpoint", "health"], &RetryPolicy::default(), &registry).await?;
+/// ```
+pub async fn run_with_policy(
+    cmd: &str,
+    args: &[&str],
+    policy: &RetryPolicy,
+    registry: &CircuitBreakerRegistry,
+) -> Result<String> {
+    for attempt in 0..policy.max_attempts {
+        if !registry.allow(cmd) {
+            return Err(CircuitOpen { cmd: cmd.to_string() }.into());
+        }
+
+        match run_checked(cmd, args).await {
+            Ok(output) => {
+                registry.record_success(cmd);
+                return Ok(output);
+            }
+            Err(e) => {
+                registry.record_failure(cmd);
+
+                let is_last = attempt + 1 == policy.max_attempts;
+                if is_last {
+                    return Err(e);
+                }
+
+                let delay = policy.delay_for_attempt(attempt);
+                warn!(cmd, attempt, ?delay, error = %e, "command failed, retrying");
+                sleep(delay).await;
+            }
+        }
+    }
+
+    unreachable!("loop always returns before exhausting max_attempts")
+}
+
 /// Run a command with sudo.
 ///
 /// # Example
@@ -2,10 +2,65 @@
 //!
 //! Provides ergonomic helpers for reading configuration from environment variables.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::env;
+use std::path::Path;
 use std::str::FromStr;
 
+/// Load a `.env`-style file into the process environment before any
+/// `env_required`/`env_parse` calls read it, giving local/dev parity with
+/// the Railway deployment (which injects these variables directly).
+///
+/// Picks `.env.<ENV>` (e.g. `.env.production` when `ENV=production`) if
+/// `ENV` is set, otherwise plain `.env`. Never overrides a variable already
+/// present in the real environment - the file only fills gaps. An
+/// explicitly requested `.env.<ENV>` that doesn't exist is an error; a
+/// missing default `.env` is not, so every binary can call this
+/// unconditionally on startup.
+pub fn merge_dotenv() -> Result<()> {
+    let path = match env::var("ENV") {
+        Ok(env_name) => {
+            let candidate = format!(".env.{}", env_name);
+            if !Path::new(&candidate).exists() {
+                bail!("ENV={} is set but {} does not exist", env_name, candidate);
+            }
+            candidate
+        }
+        Err(_) => ".env".to_string(),
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(()),
+    };
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || env::var(key).is_ok() {
+            continue;
+        }
+
+        let value = value
+            .trim()
+            .trim_start_matches('"')
+            .trim_end_matches('"')
+            .trim_start_matches('\'')
+            .trim_end_matches('\'');
+
+        env::set_var(key, value);
+    }
+
+    Ok(())
+}
+
 /// Extension trait for parsing environment variables.
 ///
 /// Provides convenient methods for reading env vars with defaults, required values,
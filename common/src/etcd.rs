@@ -0,0 +1,409 @@
+//! Native etcd v3 gRPC client
+//!
+//! Wraps the `etcd-client` crate behind a typed façade so callers get
+//! structured members/errors instead of `etcdctl` stdout scraping, and reuses
+//! a single gRPC connection instead of forking a process per call.
+
+use anyhow::{Context, Result};
+use etcd_client::{Client, Compare, CompareOp, GetOptions, MemberAddOptions, PutOptions, Txn, TxnOp};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Result of probing a single endpoint's health.
+///
+/// Mirrors the `etcdctl_probe` Ok(false)-vs-Err distinction: `Unreachable`
+/// means we couldn't even talk to the endpoint, `Unhealthy` means it
+/// responded but reported itself unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointHealth {
+    Healthy,
+    Unhealthy,
+    Unreachable,
+}
+
+impl EndpointHealth {
+    pub fn is_healthy(self) -> bool {
+        matches!(self, Self::Healthy)
+    }
+}
+
+/// A cluster member as returned by `MemberList`.
+#[derive(Debug, Clone)]
+pub struct MemberInfo {
+    pub id: u64,
+    pub name: String,
+    pub peer_urls: Vec<String>,
+    pub client_urls: Vec<String>,
+    pub is_learner: bool,
+}
+
+struct CachedHealth {
+    probed_at: Instant,
+    result: EndpointHealth,
+}
+
+/// Native etcd v3 client with a small TTL cache of endpoint health results
+/// and a cache of live connections, keyed by endpoint.
+///
+/// The health cache avoids re-probing an endpoint we just found unhealthy
+/// while scanning a list of candidates (e.g. in `wait_for_any_healthy_peer`).
+/// The connection cache avoids reconnecting on every call - `etcd_client`'s
+/// `Client` is a cheap handle around a shared gRPC channel, so it's cloned
+/// out of the cache instead of re-dialing. Both caches are only effective
+/// when callers share one `EtcdClient` instance (e.g. via `Config`) instead
+/// of constructing a fresh one per call.
+pub struct EtcdClient {
+    endpoints: Vec<String>,
+    health_ttl: Duration,
+    health_cache: Mutex<HashMap<String, CachedHealth>>,
+    connections: Mutex<HashMap<String, Client>>,
+}
+
+impl EtcdClient {
+    /// Connect to the given endpoints. Endpoints are client URLs
+    /// (e.g. `http://node-1.railway.internal:2379`).
+    pub fn new(endpoints: Vec<String>) -> Self {
+        Self {
+            endpoints,
+            health_ttl: Duration::from_secs(2),
+            health_cache: Mutex::new(HashMap::new()),
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Override the default 2s health-cache TTL.
+    pub fn with_health_ttl(mut self, ttl: Duration) -> Self {
+        self.health_ttl = ttl;
+        self
+    }
+
+    async fn connect(&self, endpoint: &str) -> Result<Client> {
+        if let Some(client) = self.connections.lock().unwrap().get(endpoint) {
+            return Ok(client.clone());
+        }
+
+        let client = Client::connect([endpoint], None)
+            .await
+            .with_context(|| format!("Failed to connect to etcd endpoint {}", endpoint))?;
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(endpoint.to_string(), client.clone());
+        Ok(client)
+    }
+
+    /// Probe a single endpoint's health, bypassing the cache.
+    pub async fn endpoint_health(&self, endpoint: &str) -> EndpointHealth {
+        let mut client = match self.connect(endpoint).await {
+            Ok(c) => c,
+            Err(e) => {
+                debug!(endpoint, error = %e, "endpoint unreachable");
+                return EndpointHealth::Unreachable;
+            }
+        };
+
+        match client.status().await {
+            Ok(_) => EndpointHealth::Healthy,
+            Err(e) => {
+                debug!(endpoint, error = %e, "endpoint reported unhealthy");
+                EndpointHealth::Unhealthy
+            }
+        }
+    }
+
+    /// Probe an endpoint, preferring a fresh cached result over a live call.
+    ///
+    /// A cached `Unhealthy`/`Unreachable` result younger than `health_ttl` is
+    /// returned as-is; otherwise this performs a live probe and refreshes the
+    /// cache entry.
+    pub async fn endpoint_health_cached(&self, endpoint: &str) -> EndpointHealth {
+        if let Some(cached) = self.health_cache.lock().unwrap().get(endpoint) {
+            if cached.probed_at.elapsed() < self.health_ttl {
+                return cached.result;
+            }
+        }
+
+        let result = self.endpoint_health(endpoint).await;
+        self.health_cache.lock().unwrap().insert(
+            endpoint.to_string(),
+            CachedHealth {
+                probed_at: Instant::now(),
+                result,
+            },
+        );
+        result
+    }
+
+    /// Find the first endpoint among `candidates` that is currently healthy,
+    /// skipping any whose cached result is fresh-and-unhealthy.
+    pub async fn first_healthy(&self, candidates: &[String]) -> Option<String> {
+        for endpoint in candidates {
+            if self.endpoint_health_cached(endpoint).await.is_healthy() {
+                return Some(endpoint.clone());
+            }
+        }
+        None
+    }
+
+    /// List cluster members via any configured endpoint.
+    pub async fn member_list(&self) -> Result<Vec<MemberInfo>> {
+        let endpoint = self
+            .first_healthy(&self.endpoints)
+            .await
+            .context("No healthy etcd endpoint available")?;
+        self.member_list_via(&endpoint).await
+    }
+
+    /// List cluster members via a specific endpoint.
+    pub async fn member_list_via(&self, endpoint: &str) -> Result<Vec<MemberInfo>> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .member_list()
+            .await
+            .context("member_list RPC failed")?;
+
+        Ok(resp
+            .members()
+            .iter()
+            .map(|m| MemberInfo {
+                id: m.id(),
+                name: m.name().to_string(),
+                peer_urls: m.peer_urls().iter().map(|s| s.to_string()).collect(),
+                client_urls: m.client_urls().iter().map(|s| s.to_string()).collect(),
+                is_learner: m.is_learner(),
+            })
+            .collect())
+    }
+
+    /// Add a new member as a non-voting learner.
+    pub async fn member_add_as_learner(
+        &self,
+        endpoint: &str,
+        peer_url: &str,
+    ) -> Result<Vec<MemberInfo>> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .member_add(
+                [peer_url],
+                Some(MemberAddOptions::new().with_is_learner(true)),
+            )
+            .await
+            .context("member_add (learner) RPC failed")?;
+
+        Ok(resp
+            .members()
+            .iter()
+            .map(|m| MemberInfo {
+                id: m.id(),
+                name: m.name().to_string(),
+                peer_urls: m.peer_urls().iter().map(|s| s.to_string()).collect(),
+                client_urls: m.client_urls().iter().map(|s| s.to_string()).collect(),
+                is_learner: m.is_learner(),
+            })
+            .collect())
+    }
+
+    /// Promote a learner to a voting member.
+    pub async fn member_promote(&self, endpoint: &str, member_id: u64) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client
+            .member_promote(member_id)
+            .await
+            .context("member_promote RPC failed")?;
+        Ok(())
+    }
+
+    /// Remove a member by ID.
+    pub async fn member_remove(&self, endpoint: &str, member_id: u64) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client
+            .member_remove(member_id)
+            .await
+            .context("member_remove RPC failed")?;
+        Ok(())
+    }
+
+    /// Put a key/value pair.
+    pub async fn put(&self, endpoint: &str, key: &str, value: &str) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client
+            .put(key, value, None::<PutOptions>)
+            .await
+            .context("put RPC failed")?;
+        Ok(())
+    }
+
+    /// Get a key's value, if present.
+    pub async fn get(&self, endpoint: &str, key: &str) -> Result<Option<String>> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .get(key, None::<GetOptions>)
+            .await
+            .context("get RPC failed")?;
+
+        Ok(resp
+            .kvs()
+            .first()
+            .map(|kv| String::from_utf8_lossy(kv.value()).to_string()))
+    }
+
+    /// Get all key/value pairs under `prefix`, keyed by the part of the key
+    /// after the prefix.
+    pub async fn get_prefix(&self, endpoint: &str, prefix: &str) -> Result<HashMap<String, String>> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .get(prefix, Some(GetOptions::new().with_prefix()))
+            .await
+            .context("get (prefix) RPC failed")?;
+
+        Ok(resp
+            .kvs()
+            .iter()
+            .map(|kv| {
+                let key = String::from_utf8_lossy(kv.key()).to_string();
+                let suffix = key.strip_prefix(prefix).unwrap_or(&key).to_string();
+                (suffix, String::from_utf8_lossy(kv.value()).to_string())
+            })
+            .collect())
+    }
+
+    /// Get the raft applied index reported by an endpoint's `Status` RPC,
+    /// used to gauge how caught-up a learner is before promoting it.
+    pub async fn applied_index(&self, endpoint: &str) -> Result<u64> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client.status().await.context("status RPC failed")?;
+        Ok(resp.raft_applied_index())
+    }
+
+    /// Get the backend DB size (in bytes) reported by an endpoint's `Status`
+    /// RPC, used to measure how much a defrag actually reclaimed.
+    pub async fn db_size(&self, endpoint: &str) -> Result<i64> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client.status().await.context("status RPC failed")?;
+        Ok(resp.db_size())
+    }
+
+    /// Defragment a single member's backend store to reclaim space freed by
+    /// compaction.
+    ///
+    /// Defrag blocks that member's writes for its duration, so callers must
+    /// only ever run this against one member at a time - never the whole
+    /// cluster simultaneously, or quorum writes would stall.
+    pub async fn defragment(&self, endpoint: &str) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client.defragment(None).await.context("defragment RPC failed")?;
+        Ok(())
+    }
+
+    /// Check whether a member currently has a NOSPACE alarm raised.
+    pub async fn has_nospace_alarm(&self, endpoint: &str) -> Result<bool> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .alarm(etcd_client::AlarmAction::Get, etcd_client::AlarmType::Nospace, None)
+            .await
+            .context("alarm (get) RPC failed")?;
+        Ok(!resp.alarms().is_empty())
+    }
+
+    /// Disarm a member's NOSPACE alarm - only safe to call once enough space
+    /// has actually been reclaimed (e.g. right after a defrag), since etcd
+    /// re-raises the alarm on the next check otherwise.
+    pub async fn disarm_nospace_alarm(&self, endpoint: &str) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client
+            .alarm(etcd_client::AlarmAction::Deactivate, etcd_client::AlarmType::Nospace, None)
+            .await
+            .context("alarm (deactivate) RPC failed")?;
+        Ok(())
+    }
+
+    /// Revoke a lease immediately instead of waiting out its TTL - used when
+    /// a lease turns out to hold nothing (e.g. lost a claim) or its holder is
+    /// giving it up intentionally.
+    pub async fn revoke_lease(&self, endpoint: &str, lease_id: i64) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        client
+            .lease_revoke(lease_id)
+            .await
+            .context("lease_revoke RPC failed")?;
+        Ok(())
+    }
+
+    /// Grant a lease with the given TTL, returning its ID.
+    pub async fn grant_lease(&self, endpoint: &str, ttl_secs: i64) -> Result<i64> {
+        let mut client = self.connect(endpoint).await?;
+        let resp = client
+            .lease_grant(ttl_secs, None)
+            .await
+            .context("lease_grant RPC failed")?;
+        Ok(resp.id())
+    }
+
+    /// Keep a lease alive in the background for as long as the returned task
+    /// isn't aborted. Intended for leases backing an election key: dropping
+    /// (aborting) the task lets the lease expire and the key get reclaimed.
+    pub fn keep_lease_alive(&self, endpoint: &str, lease_id: i64) -> tokio::task::JoinHandle<()> {
+        let endpoint = endpoint.to_string();
+        tokio::spawn(async move {
+            let client = EtcdClient::new(vec![]);
+            loop {
+                match client.connect(&endpoint).await {
+                    Ok(mut conn) => match conn.lease_keep_alive(lease_id).await {
+                        Ok((mut keeper, mut stream)) => loop {
+                            if keeper.keep_alive().await.is_err() {
+                                break;
+                            }
+                            match stream.message().await {
+                                Ok(Some(_)) => {}
+                                _ => break,
+                            }
+                            tokio::time::sleep(Duration::from_secs(3)).await;
+                        },
+                        Err(e) => debug!(endpoint = %endpoint, error = %e, "lease_keep_alive RPC failed"),
+                    },
+                    Err(e) => debug!(endpoint = %endpoint, error = %e, "keep-alive connect failed"),
+                }
+                // Connection or stream dropped (endpoint blip) - back off and retry
+                // so a transient network hiccup doesn't let the lease expire early.
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+    }
+
+    /// Atomically claim `key` for `value` under `lease_id`, but only if `key`
+    /// doesn't already exist (`create_revision == 0`). Returns `true` if this
+    /// call won the claim, `false` if someone else already holds it.
+    pub async fn try_claim(&self, endpoint: &str, key: &str, value: &str, lease_id: i64) -> Result<bool> {
+        let mut client = self.connect(endpoint).await?;
+        let txn = Txn::new()
+            .when(vec![Compare::create_revision(key, CompareOp::Equal, 0)])
+            .and_then(vec![TxnOp::put(
+                key,
+                value,
+                Some(PutOptions::new().with_lease(lease_id)),
+            )])
+            .or_else(vec![TxnOp::get(key, None)]);
+
+        let resp = client.txn(txn).await.context("txn RPC failed")?;
+        Ok(resp.succeeded())
+    }
+
+    /// Block until `key` is deleted (e.g. an election key whose holder's
+    /// lease expired), or until etcd closes the watch stream.
+    pub async fn watch_until_deleted(&self, endpoint: &str, key: &str) -> Result<()> {
+        let mut client = self.connect(endpoint).await?;
+        let (_watcher, mut stream) = client.watch(key, None).await.context("watch RPC failed")?;
+
+        while let Some(resp) = stream.message().await.context("watch stream failed")? {
+            for event in resp.events() {
+                if event.event_type() == etcd_client::EventType::Delete {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -5,13 +5,29 @@
 //! - Environment variable parsing helpers
 //! - Command execution utilities
 //! - Telemetry for reporting events to Railway
+//! - Native etcd v3 gRPC client with health caching
+//! - Connection-pooled native PostgreSQL client
+//! - Continuous cross-component cluster-invariant auditing
+//! - Role password rotation primitives
 
+pub mod audit;
+pub mod circuit;
 pub mod command;
 pub mod config;
+pub mod etcd;
 pub mod logging;
+pub mod pg;
+pub mod proxy_metrics;
+pub mod rotation;
 pub mod telemetry;
 
-pub use command::etcdctl;
-pub use config::{ConfigExt, RailwayEnv};
+pub use audit::{AuditConfig, AuditNode, ClusterAudit};
+pub use circuit::{CircuitBreakerRegistry, CircuitOpen, CircuitState, RetryPolicy};
+pub use command::{etcdctl, run_with_policy};
+pub use config::{merge_dotenv, ConfigExt, RailwayEnv};
+pub use etcd::{EndpointHealth, EtcdClient, MemberInfo as EtcdMemberInfo};
 pub use logging::init_logging;
+pub use pg::{quote_ident, quote_literal, Pg, PgCredentials, PgError, PgSession, PgSessionBuilder};
+pub use proxy_metrics::ProxyMetricsScraper;
+pub use rotation::{alter_role_password, generate_password};
 pub use telemetry::{Telemetry, TelemetryEvent};
@@ -0,0 +1,221 @@
+//! Connection-pooled native PostgreSQL client
+//!
+//! Replaces the `psql` shell-out for health checks and bootstrap SQL with a
+//! warm connection pool, so repeated probes pay only a borrow from the pool
+//! instead of a process-spawn.
+
+use deadpool_postgres::{Config as PoolConfig, Pool, Runtime};
+use thiserror::Error;
+use tokio_postgres::{NoTls, Row};
+use tracing::debug;
+
+/// Errors from the pooled Postgres client, classified so callers can retry
+/// connection/transport failures but must not blindly retry SQL errors.
+#[derive(Debug, Error)]
+pub enum PgError {
+    /// Couldn't get a connection from the pool, or the connection died
+    /// mid-use. Safe to retry.
+    #[error("connection error: {0}")]
+    Connection(String),
+
+    /// The server rejected the query (constraint violation, syntax error,
+    /// etc). Carries the SQLSTATE code when available. Not safe to blindly
+    /// retry.
+    #[error("query error ({code}): {message}")]
+    Query { code: String, message: String },
+}
+
+impl From<deadpool_postgres::PoolError> for PgError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        Self::Connection(e.to_string())
+    }
+}
+
+impl From<tokio_postgres::Error> for PgError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        match e.code() {
+            Some(state) => Self::Query {
+                code: state.code().to_string(),
+                message: e.to_string(),
+            },
+            None => Self::Connection(e.to_string()),
+        }
+    }
+}
+
+/// Quotes `ident` as a Postgres identifier (double-quoted, embedded quotes
+/// doubled). Role and database names usually come from trusted config, not
+/// user input, but DDL can't bind them as query parameters, so they still
+/// need this before going into a SQL string.
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Quotes `value` as a Postgres string literal (single-quoted, embedded
+/// quotes doubled). Used for passwords: `batch_execute`/`run_script` have no
+/// parameter binding, so a value containing a `'` would otherwise break out
+/// of the surrounding SQL.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Credentials used to connect, taken from the same fields the Patroni
+/// config already holds.
+pub struct PgCredentials {
+    pub user: String,
+    pub password: String,
+    pub dbname: String,
+    pub socket_dir: String,
+    /// Port Postgres is listening on, e.g. to reach a non-default-port
+    /// instance over its Unix socket (`.s.PGSQL.<port>`). `None` uses
+    /// `deadpool_postgres`'s default of 5432.
+    pub port: Option<u16>,
+}
+
+/// Pooled PostgreSQL client.
+#[derive(Clone)]
+pub struct Pg {
+    pool: Pool,
+}
+
+impl Pg {
+    /// Build a connection pool against the given credentials.
+    ///
+    /// Connects over the Unix socket at `socket_dir` (e.g.
+    /// `/var/run/postgresql`) rather than TCP.
+    pub fn new(creds: PgCredentials) -> Result<Self, PgError> {
+        let mut cfg = PoolConfig::new();
+        cfg.host = Some(creds.socket_dir);
+        cfg.user = Some(creds.user);
+        cfg.password = Some(creds.password);
+        cfg.dbname = Some(creds.dbname);
+        if let Some(port) = creds.port {
+            cfg.port = Some(port);
+        }
+
+        let pool = cfg
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .map_err(|e| PgError::Connection(e.to_string()))?;
+
+        Ok(Self { pool })
+    }
+
+    /// Borrow a connection directly from the pool.
+    ///
+    /// Use this instead of `query`/`execute` when a caller needs several
+    /// statements on the same session (transactions, advisory locks); those
+    /// methods each borrow a fresh connection per call, which would silently
+    /// scope a lock or transaction to a single statement.
+    pub async fn client(&self) -> Result<deadpool_postgres::Client, PgError> {
+        Ok(self.pool.get().await?)
+    }
+
+    /// Run a query, returning all matching rows.
+    pub async fn query(&self, sql: &str, params: &[&(dyn tokio_postgres::types::ToSql + Sync)]) -> Result<Vec<Row>, PgError> {
+        debug!(sql, "running query");
+        let client = self.pool.get().await?;
+        Ok(client.query(sql, params).await?)
+    }
+
+    /// Run a query expected to return exactly one row.
+    pub async fn query_one(&self, sql: &str, params: &[&(dyn tokio_postgres::types::ToSql + Sync)]) -> Result<Row, PgError> {
+        debug!(sql, "running query_one");
+        let client = self.pool.get().await?;
+        Ok(client.query_one(sql, params).await?)
+    }
+
+    /// Run a statement that doesn't return rows, returning the affected
+    /// row count.
+    pub async fn execute(&self, sql: &str, params: &[&(dyn tokio_postgres::types::ToSql + Sync)]) -> Result<u64, PgError> {
+        debug!(sql, "running execute");
+        let client = self.pool.get().await?;
+        Ok(client.execute(sql, params).await?)
+    }
+}
+
+/// Startup/connection parameters that must be set on the connection itself
+/// rather than forwarded as a generic startup parameter.
+const RESERVED_PARAMS: &[&str] = &["user", "database", "client_encoding", "options"];
+
+/// Builder for a standalone, unpooled native Postgres session - for one-off
+/// bootstrap work (running a superuser setup script) where borrowing from
+/// `Pg`'s pool isn't worth it. Mirrors how neon's proxy builds its
+/// connection config: arbitrary startup parameters go through `param`,
+/// while the handful of keys Postgres requires on the connection itself are
+/// filtered out rather than forwarded.
+pub struct PgSessionBuilder {
+    socket_dir: String,
+    user: String,
+    password: String,
+    dbname: String,
+    params: Vec<(String, String)>,
+}
+
+impl PgSessionBuilder {
+    /// Connect over the Unix socket at `socket_dir` as `user` against `dbname`.
+    pub fn new(socket_dir: impl Into<String>, user: impl Into<String>, password: impl Into<String>, dbname: impl Into<String>) -> Self {
+        Self {
+            socket_dir: socket_dir.into(),
+            user: user.into(),
+            password: password.into(),
+            dbname: dbname.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Set an arbitrary session parameter (e.g. `application_name`,
+    /// `search_path`). No-op for `user`/`database`/`client_encoding`/
+    /// `options`, which are always set via the connection itself.
+    pub fn param(mut self, key: &str, value: &str) -> Self {
+        if !RESERVED_PARAMS.contains(&key) {
+            self.params.push((key.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Connect and apply any configured `param`s.
+    pub async fn connect(self) -> Result<PgSession, PgError> {
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(&self.socket_dir)
+            .user(&self.user)
+            .password(&self.password)
+            .dbname(&self.dbname);
+
+        let (client, connection) = config.connect(NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::warn!(error = %e, "postgres session connection closed with error");
+            }
+        });
+
+        for (key, value) in &self.params {
+            client
+                .batch_execute(&format!("SET {} = '{}'", key, value.replace('\'', "''")))
+                .await?;
+        }
+
+        Ok(PgSession { client })
+    }
+}
+
+/// A standalone (unpooled) native Postgres session. See `PgSessionBuilder`.
+pub struct PgSession {
+    client: tokio_postgres::Client,
+}
+
+impl PgSession {
+    /// Run a single, unparameterized statement and return its rows.
+    pub async fn run_sql(&self, sql: &str) -> Result<Vec<Row>, PgError> {
+        Ok(self.client.query(sql, &[]).await?)
+    }
+
+    /// Run a (potentially multi-statement) script via the simple query
+    /// protocol. Postgres implicitly wraps a multi-statement simple query
+    /// in `BEGIN`/`COMMIT` and aborts the whole batch on the first error -
+    /// equivalent to `psql -v ON_ERROR_STOP=1`, without spawning `psql`.
+    pub async fn run_script(&self, sql: &str) -> Result<(), PgError> {
+        self.client.batch_execute(sql).await?;
+        Ok(())
+    }
+}
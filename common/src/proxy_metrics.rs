@@ -0,0 +1,141 @@
+//! Proxy-layer telemetry bridge
+//!
+//! Periodically scrapes HAProxy's built-in Prometheus exporter (enabled on
+//! the stats listener's `/metrics` path by `haproxy::generate_config`) and
+//! re-emits the salient server-status transitions - a server going DOWN, or
+//! the primary backend's routed server changing - as `TelemetryEvent`s, so
+//! the same Railway telemetry pipeline the etcd bootstrap path already uses
+//! also carries proxy-layer health. Gives operators a standard `/metrics`
+//! surface without a separate exporter sidecar.
+
+use crate::telemetry::{Telemetry, TelemetryEvent};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+/// One `metric{label="value",...} number` line from the Prometheus text
+/// exposition format - just enough to read HAProxy's exporter output,
+/// not a general-purpose parser.
+fn parse_metric_line(line: &str) -> Option<(&str, HashMap<&str, &str>, f64)> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (name_and_labels, value_str) = line.rsplit_once(' ')?;
+    let value: f64 = value_str.parse().ok()?;
+
+    let (name, labels) = match name_and_labels.split_once('{') {
+        Some((name, rest)) => (name, rest.trim_end_matches('}')),
+        None => (name_and_labels, ""),
+    };
+
+    let mut labels_map = HashMap::new();
+    for pair in labels.split(',').filter(|s| !s.is_empty()) {
+        if let Some((key, value)) = pair.split_once('=') {
+            labels_map.insert(key.trim(), value.trim().trim_matches('"'));
+        }
+    }
+
+    Some((name, labels_map, value))
+}
+
+/// Per-server status as last scraped: HAProxy's exporter reports
+/// `haproxy_server_status{proxy,server} <code>`, where `1` is UP and
+/// anything else (DOWN, DOWN via agent, NOLB, ...) is not routable.
+fn is_up(status_code: f64) -> bool {
+    status_code == 1.0
+}
+
+/// Tracks, per backend, each server's last-known up/down state plus which
+/// server (if any) was last seen UP - the latter only meaningful for a
+/// single-writer backend like `postgresql_primary_backend`, where exactly
+/// one server is expected to be UP at a time.
+#[derive(Default)]
+struct BackendState {
+    server_up: HashMap<String, bool>,
+    current_primary: Option<String>,
+}
+
+/// Scrapes `metrics_url` (HAProxy's `/metrics` path) on `interval` and
+/// diffs each named backend's per-server status against what was last
+/// seen, emitting `TelemetryEvent::ProxyServerDown`/`ProxyPrimaryChanged`
+/// only on the transitions themselves - a backend that stays healthy, or
+/// stays down, never re-emits.
+pub struct ProxyMetricsScraper {
+    metrics_url: String,
+    http: reqwest::Client,
+    interval: Duration,
+    /// Backends where exactly one UP server is expected; a change in which
+    /// server that is gets reported as `ProxyPrimaryChanged`.
+    single_writer_backends: Vec<String>,
+}
+
+impl ProxyMetricsScraper {
+    pub fn new(metrics_url: impl Into<String>, interval: Duration, single_writer_backends: Vec<String>) -> Self {
+        Self {
+            metrics_url: metrics_url.into(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .expect("building a plain timeout-only client never fails"),
+            interval,
+            single_writer_backends,
+        }
+    }
+
+    async fn scrape(&self) -> anyhow::Result<String> {
+        Ok(self.http.get(&self.metrics_url).send().await?.text().await?)
+    }
+
+    /// Run forever, diffing each scrape against the last one.
+    pub async fn run(self, telemetry: Telemetry) {
+        let mut backends: HashMap<String, BackendState> = HashMap::new();
+        let mut ticker = tokio::time::interval(self.interval);
+
+        loop {
+            ticker.tick().await;
+
+            let body = match self.scrape().await {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!(error = %e, "failed to scrape HAProxy Prometheus exporter");
+                    continue;
+                }
+            };
+
+            for line in body.lines() {
+                let Some((name, labels, value)) = parse_metric_line(line) else {
+                    continue;
+                };
+                if name != "haproxy_server_status" {
+                    continue;
+                }
+                let (Some(&backend), Some(&server)) = (labels.get("proxy"), labels.get("server")) else {
+                    continue;
+                };
+
+                let state = backends.entry(backend.to_string()).or_default();
+                let up = is_up(value);
+                let was_up = state.server_up.insert(server.to_string(), up);
+
+                if was_up == Some(true) && !up {
+                    telemetry.send(TelemetryEvent::ProxyServerDown {
+                        backend: backend.to_string(),
+                        server: server.to_string(),
+                    });
+                }
+
+                if self.single_writer_backends.iter().any(|b| b == backend) && up {
+                    if state.current_primary.as_deref() != Some(server) {
+                        telemetry.send(TelemetryEvent::ProxyPrimaryChanged {
+                            backend: backend.to_string(),
+                            previous_server: state.current_primary.clone(),
+                            new_server: server.to_string(),
+                        });
+                        state.current_primary = Some(server.to_string());
+                    }
+                }
+            }
+        }
+    }
+}
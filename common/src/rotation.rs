@@ -0,0 +1,43 @@
+//! Password rotation primitives shared by components that roll role
+//! credentials without a full re-bootstrap.
+//!
+//! Scope is deliberately narrow: generate a new random password and apply
+//! it to a role via `ALTER ROLE ... WITH PASSWORD`. Everything
+//! component-specific - which roles are due, rewriting the rendered
+//! config, deciding whether the encryption path is active - belongs to
+//! the caller, the same way `migrations` owns the SQL for initial role
+//! creation while this crate only owns the pooled connection it runs on.
+
+use crate::pg::{quote_ident, quote_literal};
+use crate::Pg;
+use anyhow::Result;
+use rand::Rng;
+
+const PASSWORD_LEN: usize = 32;
+const PASSWORD_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+
+/// Generates a random password for a Postgres role. Alphanumeric-only
+/// (no quotes/backslashes), so the literal-quoting `alter_role_password`
+/// already does to build the `ALTER ROLE` statement is the only escaping
+/// ever needed.
+pub fn generate_password() -> String {
+    let mut rng = rand::thread_rng();
+    (0..PASSWORD_LEN)
+        .map(|_| PASSWORD_ALPHABET[rng.gen_range(0..PASSWORD_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Applies `new_password` to `role` over `pg`'s pooled connection. Expected
+/// to run against the primary - standbys pick up the new password via
+/// streaming replication, not a direct connection from here.
+pub async fn alter_role_password(pg: &Pg, role: &str, new_password: &str) -> Result<()> {
+    let client = pg.client().await?;
+    client
+        .batch_execute(&format!(
+            "ALTER ROLE {} WITH PASSWORD {}",
+            quote_ident(role),
+            quote_literal(new_password),
+        ))
+        .await?;
+    Ok(())
+}
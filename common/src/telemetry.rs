@@ -1,15 +1,29 @@
 //! Telemetry for reporting events to Railway
 //!
-//! Provides structured event reporting to Railway's backboard service.
+//! Provides structured event reporting to Railway's backboard service. Events
+//! are handed to a long-lived background worker over a bounded queue so
+//! callers only ever pay the cost of an enqueue, never a network round trip.
 
-use crate::config::RailwayEnv;
-use reqwest::blocking::Client;
+use crate::config::{ConfigExt, RailwayEnv};
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
-use std::thread;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tracing::{info, warn};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+/// Max events buffered in memory before the queue starts dropping the oldest.
+const QUEUE_CAPACITY: usize = 512;
+/// Max events sent to Railway in a single batch.
+const MAX_BATCH_SIZE: usize = 20;
+/// How long the worker waits for more events before flushing a partial batch.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
 
 /// All telemetry events that can be sent to Railway.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +74,44 @@ pub enum TelemetryEvent {
         exit_code: Option<i32>,
     },
 
+    /// In-place major version upgrade started
+    MajorUpgradeStarted {
+        node: String,
+        from_version: String,
+        to_version: String,
+    },
+
+    /// In-place major version upgrade completed successfully
+    MajorUpgradeCompleted {
+        node: String,
+        from_version: String,
+        to_version: String,
+    },
+
+    /// In-place major version upgrade failed
+    MajorUpgradeFailed {
+        node: String,
+        from_version: String,
+        to_version: String,
+        error: String,
+    },
+
+    /// A replica's lag vs. the leader exceeded the configured threshold for
+    /// too many consecutive checks, triggering a reinitialize
+    ReplicationLagExceeded {
+        node: String,
+        lag_bytes: u64,
+        threshold_bytes: u64,
+    },
+
+    /// A replica's timeline diverged from the leader's, triggering a
+    /// reinitialize
+    TimelineDiverged {
+        node: String,
+        local_timeline: u64,
+        leader_timeline: u64,
+    },
+
     // === etcd Events ===
     /// etcd cluster bootstrap initiated
     EtcdBootstrap {
@@ -77,9 +129,15 @@ pub enum TelemetryEvent {
     /// Stale member entry removed
     EtcdStaleMemberRemoved { node: String, removed_id: String },
 
+    /// Local data directory wiped (stale/partial state)
+    EtcdDataCleared { node: String, reason: String },
+
     /// Entering recovery mode
     EtcdRecoveryMode { node: String, reason: String },
 
+    /// Node deregistered itself from the cluster during graceful shutdown
+    EtcdGracefulShutdown { node: String, removed_id: String },
+
     /// Startup attempt failed
     EtcdStartupFailed {
         node: String,
@@ -88,6 +146,9 @@ pub enum TelemetryEvent {
         error: String,
     },
 
+    /// A member's NOSPACE alarm was disarmed after defragmenting
+    EtcdAlarmCleared { node: String, alarm: String },
+
     // === HAProxy Events ===
     /// HAProxy started successfully
     HaproxyStarted { node_count: usize, single_node_mode: bool },
@@ -95,6 +156,9 @@ pub enum TelemetryEvent {
     /// HAProxy config generation starting
     HaproxyConfigGenerating { nodes: Vec<String> },
 
+    /// HAProxy backend has no healthy servers to route to (e.g. no primary)
+    DcsUnavailable { node: String, scope: String },
+
     // === Generic Events ===
     /// Component started
     ComponentStarted { component: String, version: String },
@@ -105,6 +169,34 @@ pub enum TelemetryEvent {
         error: String,
         context: String,
     },
+
+    /// A cluster-wide invariant checked by `audit` failed (e.g. etcd lost
+    /// quorum, two nodes both report primary, a backend has no reachable
+    /// server).
+    AuditFailed { check: String, detail: String },
+
+    /// A server in a proxy backend (as scraped from HAProxy's Prometheus
+    /// exporter by `proxy_metrics`) flipped from UP to DOWN.
+    ProxyServerDown { backend: String, server: String },
+
+    /// The server HAProxy's primary backend routes to changed - a failover
+    /// or switchover as observed from the proxy layer, independent of (and
+    /// a cross-check against) the Patroni-side `PostgresFailover` event.
+    ProxyPrimaryChanged {
+        backend: String,
+        previous_server: Option<String>,
+        new_server: String,
+    },
+
+    /// Rotation applied a new password for each of `roles` on `node`.
+    CredentialsRotated { roles: Vec<String>, node: String },
+
+    /// Rotation failed partway through for `roles` on `node`.
+    RotationFailed {
+        roles: Vec<String>,
+        node: String,
+        error: String,
+    },
 }
 
 impl TelemetryEvent {
@@ -119,16 +211,30 @@ impl TelemetryEvent {
             Self::SslRenewed { .. } => "POSTGRES_HA_SSL_RENEWED",
             Self::HealthCheckFailed { .. } => "POSTGRES_HA_HEALTH_CHECK_FAILED",
             Self::ProcessDied { .. } => "POSTGRES_HA_PROCESS_DIED",
+            Self::MajorUpgradeStarted { .. } => "POSTGRES_HA_MAJOR_UPGRADE_STARTED",
+            Self::MajorUpgradeCompleted { .. } => "POSTGRES_HA_MAJOR_UPGRADE_COMPLETED",
+            Self::MajorUpgradeFailed { .. } => "POSTGRES_HA_MAJOR_UPGRADE_FAILED",
+            Self::ReplicationLagExceeded { .. } => "POSTGRES_HA_REPLICATION_LAG_EXCEEDED",
+            Self::TimelineDiverged { .. } => "POSTGRES_HA_TIMELINE_DIVERGED",
             Self::EtcdBootstrap { .. } => "ETCD_CLUSTER_BOOTSTRAP",
             Self::EtcdNodeJoined { .. } => "ETCD_NODE_JOINED",
             Self::EtcdNodePromoted { .. } => "ETCD_NODE_PROMOTED",
             Self::EtcdStaleMemberRemoved { .. } => "ETCD_STALE_MEMBER_REMOVED",
+            Self::EtcdDataCleared { .. } => "ETCD_DATA_CLEARED",
             Self::EtcdRecoveryMode { .. } => "ETCD_RECOVERY_MODE",
+            Self::EtcdGracefulShutdown { .. } => "ETCD_GRACEFUL_SHUTDOWN",
             Self::EtcdStartupFailed { .. } => "ETCD_STARTUP_FAILED",
+            Self::EtcdAlarmCleared { .. } => "ETCD_ALARM_CLEARED",
             Self::HaproxyStarted { .. } => "HAPROXY_STARTED",
             Self::HaproxyConfigGenerating { .. } => "HAPROXY_CONFIG_GENERATING",
+            Self::DcsUnavailable { .. } => "HAPROXY_DCS_UNAVAILABLE",
             Self::ComponentStarted { .. } => "COMPONENT_STARTED",
             Self::ComponentError { .. } => "COMPONENT_ERROR",
+            Self::AuditFailed { .. } => "POSTGRES_HA_AUDIT_FAILED",
+            Self::ProxyServerDown { .. } => "HAPROXY_PROXY_SERVER_DOWN",
+            Self::ProxyPrimaryChanged { .. } => "HAPROXY_PROXY_PRIMARY_CHANGED",
+            Self::CredentialsRotated { .. } => "POSTGRES_HA_CREDENTIALS_ROTATED",
+            Self::RotationFailed { .. } => "POSTGRES_HA_ROTATION_FAILED",
         }
     }
 
@@ -173,6 +279,43 @@ impl TelemetryEvent {
                     process, node, exit_code
                 )
             }
+            Self::MajorUpgradeStarted { node, from_version, to_version } => {
+                format!("{} starting in-place upgrade from PostgreSQL {} to {}", node, from_version, to_version)
+            }
+            Self::MajorUpgradeCompleted { node, from_version, to_version } => {
+                format!("{} upgraded from PostgreSQL {} to {}", node, from_version, to_version)
+            }
+            Self::MajorUpgradeFailed {
+                node,
+                from_version,
+                to_version,
+                error,
+            } => {
+                format!(
+                    "{} failed upgrading from PostgreSQL {} to {}: {}",
+                    node, from_version, to_version, error
+                )
+            }
+            Self::ReplicationLagExceeded {
+                node,
+                lag_bytes,
+                threshold_bytes,
+            } => {
+                format!(
+                    "{} replication lag {} bytes exceeded threshold of {} bytes, reinitializing",
+                    node, lag_bytes, threshold_bytes
+                )
+            }
+            Self::TimelineDiverged {
+                node,
+                local_timeline,
+                leader_timeline,
+            } => {
+                format!(
+                    "{} timeline {} diverged from leader timeline {}, reinitializing",
+                    node, local_timeline, leader_timeline
+                )
+            }
             Self::EtcdBootstrap {
                 node,
                 is_leader,
@@ -192,9 +335,15 @@ impl TelemetryEvent {
             Self::EtcdStaleMemberRemoved { node, removed_id } => {
                 format!("etcd {} removed stale member {}", node, removed_id)
             }
+            Self::EtcdDataCleared { node, reason } => {
+                format!("etcd {} data directory cleared: {}", node, reason)
+            }
             Self::EtcdRecoveryMode { node, reason } => {
                 format!("etcd {} recovery mode: {}", node, reason)
             }
+            Self::EtcdGracefulShutdown { node, removed_id } => {
+                format!("etcd {} deregistered itself (member {}) during graceful shutdown", node, removed_id)
+            }
             Self::EtcdStartupFailed {
                 node,
                 attempt,
@@ -206,6 +355,9 @@ impl TelemetryEvent {
                     node, attempt, max_attempts, error
                 )
             }
+            Self::EtcdAlarmCleared { node, alarm } => {
+                format!("etcd {} disarmed {} alarm after defragmentation", node, alarm)
+            }
             Self::HaproxyStarted {
                 node_count,
                 single_node_mode,
@@ -218,6 +370,9 @@ impl TelemetryEvent {
             Self::HaproxyConfigGenerating { nodes } => {
                 format!("Generating HAProxy config for: {:?}", nodes)
             }
+            Self::DcsUnavailable { node, scope } => {
+                format!("{}: no healthy primary available for {}", node, scope)
+            }
             Self::ComponentStarted { component, version } => {
                 format!("{} v{} started", component, version)
             }
@@ -228,59 +383,202 @@ impl TelemetryEvent {
             } => {
                 format!("{} error in {}: {}", component, context, error)
             }
+            Self::AuditFailed { check, detail } => {
+                format!("cluster audit '{}' failed: {}", check, detail)
+            }
+            Self::ProxyServerDown { backend, server } => {
+                format!("HAProxy backend {} server {} went DOWN", backend, server)
+            }
+            Self::ProxyPrimaryChanged { backend, previous_server, new_server } => {
+                format!(
+                    "HAProxy backend {} primary changed from {:?} to {}",
+                    backend, previous_server, new_server
+                )
+            }
+            Self::CredentialsRotated { roles, node } => {
+                format!("rotated credentials for {} on {}", roles.join(", "), node)
+            }
+            Self::RotationFailed { roles, node, error } => {
+                format!(
+                    "credential rotation failed for {} on {}: {}",
+                    roles.join(", "),
+                    node,
+                    error
+                )
+            }
+        }
+    }
+}
+
+/// Bounded, drop-oldest queue shared between `Telemetry` handles and the
+/// background worker.
+struct Queue {
+    events: Mutex<VecDeque<TelemetryEvent>>,
+    notify: Notify,
+    dropped: AtomicU64,
+}
+
+impl Queue {
+    fn new() -> Self {
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(QUEUE_CAPACITY)),
+            notify: Notify::new(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn push(&self, event: TelemetryEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() >= QUEUE_CAPACITY {
+            events.pop_front();
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!(dropped, "telemetry queue full, dropping oldest event");
+        }
+        events.push_back(event);
+        drop(events);
+        self.notify.notify_one();
+    }
+
+    /// Drain up to `MAX_BATCH_SIZE` events, waiting up to `FLUSH_INTERVAL`
+    /// for at least one to arrive if the queue is currently empty.
+    async fn next_batch(&self) -> Vec<TelemetryEvent> {
+        {
+            let events = self.events.lock().unwrap();
+            if events.is_empty() {
+                drop(events);
+                let _ = tokio::time::timeout(FLUSH_INTERVAL, self.notify.notified()).await;
+            }
         }
+
+        let mut events = self.events.lock().unwrap();
+        let drain = events.len().min(MAX_BATCH_SIZE);
+        events.drain(..drain).collect()
     }
 }
 
 /// Telemetry client for sending events to Railway.
+///
+/// Cloning is cheap: clones share the same background worker and queue.
 #[derive(Clone)]
 pub struct Telemetry {
-    client: Arc<Client>,
-    endpoint: String,
-    project_id: String,
-    environment_id: String,
-    component: String,
+    queue: Arc<Queue>,
 }
 
 impl Telemetry {
-    /// Create a new telemetry client from environment variables.
+    /// Create a new telemetry client from environment variables and spawn
+    /// its background delivery worker.
     pub fn from_env(component: &str) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(5))
             .build()
             .unwrap_or_else(|_| Client::new());
 
-        Self {
-            client: Arc::new(client),
+        let worker = Worker {
+            client,
             endpoint: RailwayEnv::graphql_endpoint(),
             project_id: RailwayEnv::project_id(),
             environment_id: RailwayEnv::environment_id(),
             component: component.to_string(),
-        }
+            spool_path: spool_path(),
+        };
+
+        let queue = Arc::new(Queue::new());
+        tokio::spawn(worker.run(Arc::clone(&queue)));
+
+        Self { queue }
     }
 
-    /// Send a telemetry event (fire and forget, non-blocking).
+    /// Enqueue a telemetry event for delivery by the background worker.
     ///
-    /// This spawns a thread to send the event asynchronously.
-    /// Errors are logged but do not affect the caller.
+    /// Never blocks on the network: if the queue is full the oldest
+    /// pending event is dropped to make room.
     pub fn send(&self, event: TelemetryEvent) {
-        let endpoint = self.endpoint.clone();
-        let client = Arc::clone(&self.client);
-        let project_id = self.project_id.clone();
-        let environment_id = self.environment_id.clone();
-        let component = self.component.clone();
+        info!(event = %event.event_type(), "{}", event.message());
+        self.queue.push(event);
+    }
+}
+
+fn spool_path() -> PathBuf {
+    PathBuf::from(String::env_or(
+        "TELEMETRY_SPOOL_PATH",
+        "/tmp/postgres-ha-telemetry.spool",
+    ))
+}
+
+/// Drives batched delivery for one `Telemetry` handle's queue.
+struct Worker {
+    client: Client,
+    endpoint: String,
+    project_id: String,
+    environment_id: String,
+    component: String,
+    spool_path: PathBuf,
+}
+
+impl Worker {
+    async fn run(self, queue: Arc<Queue>) {
+        // Replay anything spooled from a previous run before handling new events.
+        if let Err(e) = self.replay_spool().await {
+            warn!("failed to replay telemetry spool: {}", e);
+        }
+
+        loop {
+            let batch = queue.next_batch().await;
+            if batch.is_empty() {
+                continue;
+            }
+
+            match self.send_with_retry(&batch).await {
+                // The network is back - also retry anything still spooled
+                // from an earlier outage instead of waiting for the next
+                // process restart to pick it up.
+                Ok(()) => {
+                    if let Err(e) = self.replay_spool().await {
+                        warn!("failed to replay telemetry spool: {}", e);
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        events = batch.len(),
+                        "telemetry delivery failed after retries, spooling: {}", e
+                    );
+                    if let Err(e) = self.spool(&batch).await {
+                        warn!("failed to spool telemetry events to disk: {}", e);
+                    }
+                }
+            }
+        }
+    }
 
-        let event_type = event.event_type();
-        let message = event.message();
+    /// Send a batch with exponential backoff, giving up after a few attempts
+    /// so the worker can spool and move on rather than stall indefinitely.
+    async fn send_with_retry(&self, batch: &[TelemetryEvent]) -> Result<(), reqwest::Error> {
+        let mut delay = Duration::from_millis(250);
+        let max_attempts = 4;
 
-        // Log locally first
-        info!(event = %event_type, "{}", message);
+        for attempt in 1..=max_attempts {
+            match self.send_batch(batch).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    if attempt == max_attempts {
+                        return Err(e);
+                    }
+                    debug!(attempt, delay = ?delay, error = %e, "telemetry batch send failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(Duration::from_secs(10));
+                }
+            }
+        }
+
+        unreachable!("loop always returns before exhausting max_attempts")
+    }
 
-        // Serialize event data as metadata
-        let metadata = serde_json::to_string(&event).unwrap_or_default();
+    async fn send_batch(&self, batch: &[TelemetryEvent]) -> Result<(), reqwest::Error> {
+        for event in batch {
+            let event_type = event.event_type();
+            let message = event.message();
+            let metadata = serde_json::to_string(event).unwrap_or_default();
 
-        // Send asynchronously
-        thread::spawn(move || {
             let payload = json!({
                 "query": "mutation telemetrySend($input: TelemetrySendInput!) { telemetrySend(input: $input) }",
                 "variables": {
@@ -288,62 +586,76 @@ impl Telemetry {
                         "command": event_type,
                         "error": message,
                         "stacktrace": metadata,
-                        "projectId": project_id,
-                        "environmentId": environment_id,
-                        "version": component
+                        "projectId": self.project_id,
+                        "environmentId": self.environment_id,
+                        "version": self.component
                     }
                 }
             });
 
-            match client
-                .post(&endpoint)
+            let resp = self
+                .client
+                .post(&self.endpoint)
                 .header("Content-Type", "application/json")
                 .json(&payload)
                 .send()
-            {
-                Ok(resp) if resp.status().is_success() => {
-                    // Success - no action needed
-                }
-                Ok(resp) => {
-                    warn!("Telemetry got status {}", resp.status());
-                }
-                Err(e) => {
-                    warn!("Telemetry send failed: {}", e);
-                }
+                .await?;
+
+            if let Err(e) = resp.error_for_status_ref() {
+                return Err(e);
             }
-        });
+        }
+
+        Ok(())
     }
 
-    /// Send a telemetry event synchronously (blocking).
-    ///
-    /// Use this when you need to ensure the event is sent before continuing.
-    pub fn send_sync(&self, event: TelemetryEvent) -> Result<(), reqwest::Error> {
-        let event_type = event.event_type();
-        let message = event.message();
-        let metadata = serde_json::to_string(&event).unwrap_or_default();
-
-        info!(event = %event_type, "{}", message);
-
-        let payload = json!({
-            "query": "mutation telemetrySend($input: TelemetrySendInput!) { telemetrySend(input: $input) }",
-            "variables": {
-                "input": {
-                    "command": event_type,
-                    "error": message,
-                    "stacktrace": metadata,
-                    "projectId": self.project_id,
-                    "environmentId": self.environment_id,
-                    "version": self.component
-                }
+    /// Append serialized events to the spool file, one JSON object per line.
+    async fn spool(&self, batch: &[TelemetryEvent]) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.spool_path)
+            .await?;
+
+        for event in batch {
+            let line = serde_json::to_string(event).unwrap_or_default();
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await?;
+        }
+
+        Ok(())
+    }
+
+    /// On worker startup, try to flush any events left over from a prior
+    /// process that couldn't reach Railway. The spool file is truncated once
+    /// everything in it has been delivered.
+    async fn replay_spool(&self) -> anyhow::Result<()> {
+        let mut contents = String::new();
+        match OpenOptions::new().read(true).open(&self.spool_path).await {
+            Ok(mut file) => {
+                file.read_to_string(&mut contents).await?;
             }
-        });
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let events: Vec<TelemetryEvent> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
 
-        self.client
-            .post(&self.endpoint)
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        info!(count = events.len(), "replaying spooled telemetry events");
+
+        for chunk in events.chunks(MAX_BATCH_SIZE) {
+            self.send_with_retry(chunk).await?;
+        }
 
+        tokio::fs::remove_file(&self.spool_path).await?;
         Ok(())
     }
 }
@@ -0,0 +1,42 @@
+//! Decorrelated-jitter retry backoff
+//!
+//! A fixed delay between retry attempts makes every node booting at once
+//! retry in lockstep, thundering-herding the bootstrap leader. This
+//! implements decorrelated jitter (as described in AWS's "Exponential
+//! Backoff And Jitter" architecture post): each failed attempt's wait is
+//! `min(cap, random_between(base, previous * 3))`, which both spreads
+//! retries across nodes and grows the wait under sustained failure.
+
+use rand::Rng;
+use std::time::Duration;
+
+pub struct DecorrelatedJitter {
+    base: Duration,
+    cap: Duration,
+    previous: Duration,
+}
+
+impl DecorrelatedJitter {
+    pub fn new(base: Duration, cap: Duration) -> Self {
+        Self {
+            base,
+            cap,
+            previous: base,
+        }
+    }
+
+    /// Compute the next wait and advance internal state.
+    pub fn next_delay(&mut self) -> Duration {
+        let base_secs = self.base.as_secs_f64();
+        let upper = (self.previous.as_secs_f64() * 3.0).max(base_secs);
+        let secs = rand::thread_rng().gen_range(base_secs..=upper);
+        let next = Duration::from_secs_f64(secs).min(self.cap);
+        self.previous = next;
+        next
+    }
+
+    /// Reset to `base`, e.g. after a successful bootstrap stage.
+    pub fn reset(&mut self) {
+        self.previous = self.base;
+    }
+}
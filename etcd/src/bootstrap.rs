@@ -4,32 +4,41 @@
 
 use crate::cluster::{
     add_self_to_cluster, check_cluster_health, clear_directory, get_current_cluster,
-    has_local_data, promote_self, remove_stale_self,
+    get_voting_member_endpoint, has_local_data, is_learner, remove_stale_self,
 };
 use crate::config::{get_leader_endpoint, get_my_peer_url, parse_initial_cluster, peer_to_client_url, Config};
+use crate::discovery;
+use crate::failure_detector::FailureDetector;
+use crate::peer_store;
+use crate::placement;
 use anyhow::{anyhow, Result};
-use common::{etcdctl, etcdctl_probe, Telemetry, TelemetryEvent};
+use common::{Telemetry, TelemetryEvent};
 use std::path::Path;
+use std::sync::Arc;
 use tokio::fs;
 use tokio::time::sleep;
 use tracing::{info, warn};
 
-/// Check if any other peer has a healthy cluster (for recovery detection)
-pub async fn check_existing_cluster(initial_cluster: &str, my_name: &str) -> Result<Option<String>> {
+/// Check if any other peer has a healthy cluster (for recovery detection).
+/// Also tries peers only known from a previously-persisted discovery run,
+/// so a restart with a stale/unreachable `ETCD_INITIAL_CLUSTER` entry can
+/// still find a cluster that moved.
+pub async fn check_existing_cluster(config: &Config) -> Result<Option<String>> {
     info!("Checking for existing cluster on other peers...");
 
-    let cluster = parse_initial_cluster(initial_cluster)?;
+    let mut cluster = parse_initial_cluster(&config.initial_cluster)?;
+    cluster.extend(discovery::load_persisted(&config.data_dir).await);
+    let client = &config.etcd_client;
+
     for (name, peer_url) in cluster.iter() {
-        if name == my_name {
+        if name == &config.etcd_name {
             continue;
         }
 
         let client_endpoint = peer_to_client_url(peer_url);
         info!(peer = %name, endpoint = %client_endpoint, "Checking peer");
 
-        if etcdctl_probe(&["endpoint", "health", &format!("--endpoints={}", client_endpoint)])
-            .await?
-        {
+        if client.endpoint_health_cached(&client_endpoint).await.is_healthy() {
             info!(peer = %name, "Found healthy cluster");
             return Ok(Some(client_endpoint));
         }
@@ -38,24 +47,53 @@ pub async fn check_existing_cluster(initial_cluster: &str, my_name: &str) -> Res
     Ok(None)
 }
 
-/// Wait for leader or any healthy peer
+/// Wait for leader or any healthy peer.
+///
+/// Health is decided by `detector`'s phi-accrual estimate rather than a
+/// single probe, so a peer that just had one slow response isn't
+/// immediately written off.
 pub async fn wait_for_any_healthy_peer(
     config: &Config,
     preferred_leader: &str,
+    detector: &FailureDetector,
 ) -> Result<(String, String)> {
-    let cluster = parse_initial_cluster(&config.initial_cluster)?;
+    let mut cluster = parse_initial_cluster(&config.initial_cluster)?;
+    cluster.extend(discovery::load_persisted(&config.data_dir).await);
+    let client = &config.etcd_client;
+    let persisted_peers = peer_store::load(&config.data_dir).await;
 
     info!(leader = %preferred_leader, "Waiting for bootstrap leader or any healthy peer");
 
     let start = std::time::Instant::now();
     while start.elapsed() < config.peer_wait_timeout {
+        // Try previously-healthy persisted peers before falling back to
+        // full discovery - fast and survivable when `preferred_leader` is
+        // the one that's down.
+        for (name, record) in persisted_peers.iter() {
+            if name == &config.etcd_name {
+                continue;
+            }
+
+            let client_endpoint = peer_to_client_url(&record.peer_url);
+            if client.endpoint_health_cached(&client_endpoint).await.is_healthy() {
+                detector.report_heartbeat(&client_endpoint);
+            }
+            if detector.is_available(&client_endpoint) {
+                info!(peer = %name, "Found healthy persisted peer");
+                return Ok((name.clone(), client_endpoint));
+            }
+        }
+
         // Try preferred leader first
         if let Some(endpoint) = get_leader_endpoint(&config.initial_cluster, preferred_leader)? {
-            if etcdctl_probe(&["endpoint", "health", &format!("--endpoints={}", endpoint)]).await? {
+            if client.endpoint_health_cached(&endpoint).await.is_healthy() {
+                detector.report_heartbeat(&endpoint);
+            }
+            if detector.is_available(&endpoint) {
                 info!(leader = %preferred_leader, "Leader is healthy");
                 return Ok((preferred_leader.to_string(), endpoint));
             }
-            info!(leader = %preferred_leader, "Leader health check failed");
+            info!(leader = %preferred_leader, phi = detector.phi(&endpoint), "Leader health check failed");
         }
 
         // Try any other peer
@@ -65,13 +103,14 @@ pub async fn wait_for_any_healthy_peer(
             }
 
             let client_endpoint = peer_to_client_url(peer_url);
-            if etcdctl_probe(&["endpoint", "health", &format!("--endpoints={}", client_endpoint)])
-                .await?
-            {
+            if client.endpoint_health_cached(&client_endpoint).await.is_healthy() {
+                detector.report_heartbeat(&client_endpoint);
+            }
+            if detector.is_available(&client_endpoint) {
                 info!(peer = %name, "Found healthy peer");
                 return Ok((name.clone(), client_endpoint));
             }
-            info!(peer = %name, "Peer health check failed");
+            info!(peer = %name, phi = detector.phi(&client_endpoint), "Peer health check failed");
         }
 
         info!(
@@ -122,38 +161,74 @@ pub async fn clean_stale_data(config: &Config, telemetry: &Telemetry) -> Result<
     Ok(())
 }
 
-/// Monitor and mark bootstrap complete
-pub async fn monitor_and_mark_bootstrap(
-    config: &Config,
+/// Monitors cluster health after etcd starts, promotes this node from
+/// learner to voting member once caught up, and marks bootstrap complete.
+/// The first `Worker` implementation (see `worker`).
+pub struct BootstrapMonitorWorker {
+    config: Config,
     joined_as_learner: bool,
-    telemetry: Telemetry,
-) -> Result<()> {
-    let mut promoted = false;
-
-    loop {
-        sleep(std::time::Duration::from_secs(5)).await;
-
-        let is_healthy = check_cluster_health(&config.initial_cluster).await?;
-
-        if is_healthy {
-            if joined_as_learner && !promoted {
-                info!("Healthy, attempting promotion");
-                match promote_self(&config.initial_cluster, &config.etcd_name, &telemetry).await {
-                    Ok(_) => {
-                        promoted = true;
-                    }
-                    Err(e) => {
-                        warn!(error = %e, "Promotion failed, will retry");
-                    }
+    detector: Arc<FailureDetector>,
+}
+
+impl BootstrapMonitorWorker {
+    pub fn new(config: Config, joined_as_learner: bool, detector: Arc<FailureDetector>) -> Self {
+        Self {
+            config,
+            joined_as_learner,
+            detector,
+        }
+    }
+}
+
+impl crate::worker::Worker for BootstrapMonitorWorker {
+    fn name(&self) -> &str {
+        "bootstrap-monitor"
+    }
+
+    async fn step(&mut self) -> Result<crate::worker::WorkerState> {
+        use crate::worker::WorkerState;
+
+        let is_healthy =
+            check_cluster_health(&self.config.etcd_client, &self.config.initial_cluster, &self.detector).await?;
+        if !is_healthy {
+            return Ok(WorkerState::Idle);
+        }
+
+        if let Err(e) = placement::register_datacenter(
+            &self.config.etcd_client,
+            "http://127.0.0.1:2379",
+            &self.config.etcd_name,
+            &self.config.datacenter,
+        )
+        .await
+        {
+            warn!(error = %e, "Failed to register datacenter");
+        }
+
+        // Actual promotion is driven by the separate `PromotionWorker`; this
+        // only checks whether it has landed yet, so the bootstrap marker
+        // isn't written while we're still a non-voting learner.
+        let is_voter = if self.joined_as_learner {
+            match get_voting_member_endpoint(&self.config.etcd_client, &self.config.initial_cluster).await? {
+                Some(endpoint) => {
+                    !is_learner(&self.config.etcd_client, &endpoint, &self.config.etcd_name)
+                        .await
+                        .unwrap_or(true)
                 }
+                None => false,
             }
+        } else {
+            true
+        };
 
-            let marker_path = config.bootstrap_marker();
-            if !Path::new(&marker_path).exists() && (!joined_as_learner || promoted) {
-                fs::write(&marker_path, "1").await?;
-                info!("Bootstrap marked complete");
-            }
+        let marker_path = self.config.bootstrap_marker();
+        if !Path::new(&marker_path).exists() && is_voter {
+            fs::write(&marker_path, "1").await?;
+            info!("Bootstrap marked complete");
+            return Ok(WorkerState::Done);
         }
+
+        Ok(WorkerState::Idle)
     }
 }
 
@@ -181,9 +256,7 @@ pub async fn bootstrap_as_leader(
     }
 
     // Check for recovery scenario - existing cluster on other peers
-    if let Some(existing_endpoint) =
-        check_existing_cluster(&config.initial_cluster, &config.etcd_name).await?
-    {
+    if let Some(existing_endpoint) = check_existing_cluster(config).await? {
         info!("RECOVERY MODE: Found existing cluster");
 
         telemetry.send(TelemetryEvent::EtcdRecoveryMode {
@@ -194,46 +267,47 @@ pub async fn bootstrap_as_leader(
         let my_peer_url = get_my_peer_url(&config.initial_cluster, &config.etcd_name)?
             .ok_or_else(|| anyhow!("Could not find my peer URL in ETCD_INITIAL_CLUSTER"))?;
 
-        if let Err(e) = remove_stale_self(&existing_endpoint, &config.etcd_name, &my_peer_url, telemetry).await {
+        if let Err(e) =
+            remove_stale_self(&config.etcd_client, &existing_endpoint, &config.etcd_name, &my_peer_url, telemetry)
+                .await
+        {
             warn!(error = %e, "Failed to remove stale self, continuing anyway");
         }
 
-        let output = etcdctl(&[
-            "member",
-            "add",
-            &config.etcd_name,
-            "--learner",
-            &format!("--peer-urls={}", my_peer_url),
-            &format!("--endpoints={}", existing_endpoint),
-        ])
-        .await;
-
-        match output {
-            Ok(out) => {
+        let result = config
+            .etcd_client
+            .member_add_as_learner(&existing_endpoint, &my_peer_url)
+            .await;
+
+        match result {
+            Ok(members) => {
                 telemetry.send(TelemetryEvent::EtcdNodeJoined {
                     node: config.etcd_name.clone(),
                     joined_as: "learner".to_string(),
                 });
 
-                let mut cluster_str = String::new();
-                for line in out.lines() {
-                    if line.contains("ETCD_INITIAL_CLUSTER=") {
-                        if let Some(c) = line
-                            .split("ETCD_INITIAL_CLUSTER=")
-                            .nth(1)
-                            .map(|s| s.trim_matches('"').to_string())
-                        {
-                            cluster_str = c;
-                            break;
-                        }
-                    }
+                // Same as `add_self_to_cluster`: the newly-added member has no
+                // name yet, so build the cluster string from the response and
+                // append self explicitly.
+                let mut cluster_parts: Vec<String> = members
+                    .iter()
+                    .filter(|m| !m.name.is_empty())
+                    .filter_map(|m| m.peer_urls.first().map(|url| format!("{}={}", m.name, url)))
+                    .collect();
+
+                if !cluster_parts
+                    .iter()
+                    .any(|p| p.starts_with(&format!("{}=", config.etcd_name)))
+                {
+                    cluster_parts.push(format!("{}={}", config.etcd_name, my_peer_url));
                 }
 
-                if cluster_str.is_empty() {
-                    cluster_str =
-                        get_current_cluster(&existing_endpoint, &config.etcd_name, &my_peer_url)
-                            .await?;
-                }
+                let cluster_str = if cluster_parts.is_empty() {
+                    get_current_cluster(&config.etcd_client, &existing_endpoint, &config.etcd_name, &my_peer_url)
+                        .await?
+                } else {
+                    cluster_parts.join(",")
+                };
 
                 info!(cluster = %cluster_str, "Joining as learner (recovery)");
                 return Ok(Some(BootstrapParams {
@@ -274,6 +348,7 @@ pub async fn bootstrap_as_follower(
     config: &Config,
     bootstrap_leader: &str,
     telemetry: &Telemetry,
+    detector: &FailureDetector,
 ) -> Result<Option<BootstrapParams>> {
     let marker_exists = Path::new(&config.bootstrap_marker()).exists();
 
@@ -287,7 +362,7 @@ pub async fn bootstrap_as_follower(
 
     // Wait for a healthy peer
     let (healthy_peer, endpoint) =
-        match wait_for_any_healthy_peer(config, bootstrap_leader).await {
+        match wait_for_any_healthy_peer(config, bootstrap_leader, detector).await {
             Ok(result) => result,
             Err(e) => {
                 warn!(error = %e, "Failed to find healthy peer");
@@ -3,78 +3,60 @@
 //! Functions for managing cluster membership, starting etcd, and health checking.
 
 use crate::config::{get_my_peer_url, parse_initial_cluster, peer_to_client_url, Config};
+use crate::failure_detector::FailureDetector;
+use crate::placement;
 use anyhow::{anyhow, Context, Result};
-use common::{etcdctl, etcdctl_probe, Telemetry, TelemetryEvent};
+use common::{EtcdClient, EtcdMemberInfo as MemberInfo, Telemetry, TelemetryEvent};
 use std::path::Path;
 use std::process::Stdio;
 use tokio::fs;
 use tokio::process::Command;
 use tracing::{info, warn};
 
-/// Information about an etcd cluster member
-#[derive(Debug)]
-pub struct MemberInfo {
-    pub id: String,
-    pub name: String,
-    pub peer_url: String,
-    pub is_learner: bool,
-}
-
 /// Get member list from etcd
-pub async fn get_member_list(endpoint: &str) -> Result<Vec<MemberInfo>> {
-    let output = etcdctl(&[
-        "member",
-        "list",
-        &format!("--endpoints={}", endpoint),
-        "-w",
-        "simple",
-    ])
-    .await?;
-
-    let members: Vec<MemberInfo> = output
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| {
-            let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
-            if parts.len() >= 5 {
-                Ok(MemberInfo {
-                    id: parts[0].to_string(),
-                    name: parts[2].to_string(),
-                    peer_url: parts[3].to_string(),
-                    is_learner: parts.get(5).map(|s| *s == "true").unwrap_or(false),
-                })
-            } else {
-                Err(anyhow!(
-                    "Invalid member list line '{}': expected at least 5 comma-separated fields",
-                    line
-                ))
-            }
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    Ok(members)
+pub async fn get_member_list(client: &EtcdClient, endpoint: &str) -> Result<Vec<MemberInfo>> {
+    client.member_list_via(endpoint).await
 }
 
-/// Check cluster health via localhost or voting member
-pub async fn check_cluster_health(initial_cluster: &str) -> Result<bool> {
-    if etcdctl_probe(&["endpoint", "health", "--endpoints=http://127.0.0.1:2379"]).await? {
+/// Check cluster health via localhost or voting member.
+///
+/// Each live probe feeds `detector`, and the verdict comes from its
+/// phi-accrual estimate rather than the probe's own instantaneous
+/// pass/fail, so a single slow response doesn't flip the result.
+pub async fn check_cluster_health(
+    client: &EtcdClient,
+    initial_cluster: &str,
+    detector: &FailureDetector,
+) -> Result<bool> {
+    const LOCAL_ENDPOINT: &str = "http://127.0.0.1:2379";
+
+    if client.endpoint_health_cached(LOCAL_ENDPOINT).await.is_healthy() {
+        detector.report_heartbeat(LOCAL_ENDPOINT);
+    }
+    if detector.is_available(LOCAL_ENDPOINT) {
         return Ok(true);
     }
 
-    if let Some(endpoint) = get_voting_member_endpoint(initial_cluster).await? {
-        return etcdctl_probe(&["endpoint", "health", &format!("--endpoints={}", endpoint)]).await;
+    if let Some(endpoint) = get_voting_member_endpoint(client, initial_cluster).await? {
+        if client.endpoint_health_cached(&endpoint).await.is_healthy() {
+            detector.report_heartbeat(&endpoint);
+        }
+        return Ok(detector.is_available(&endpoint));
     }
 
     Ok(false)
 }
 
 /// Find a voting member endpoint
-pub async fn get_voting_member_endpoint(initial_cluster: &str) -> Result<Option<String>> {
+pub async fn get_voting_member_endpoint(
+    client: &EtcdClient,
+    initial_cluster: &str,
+) -> Result<Option<String>> {
     let cluster = parse_initial_cluster(initial_cluster)?;
 
     for (_name, peer_url) in cluster.iter() {
         let client_endpoint = peer_to_client_url(peer_url);
-        if etcdctl_probe(&["member", "list", &format!("--endpoints={}", client_endpoint)]).await? {
+        if client.member_list_via(&client_endpoint).await.is_ok() {
             return Ok(Some(client_endpoint));
         }
     }
@@ -83,8 +65,12 @@ pub async fn get_voting_member_endpoint(initial_cluster: &str) -> Result<Option<
 }
 
 /// Get my member ID from etcd cluster
-pub async fn get_my_member_id(endpoint: &str, my_name: &str) -> Result<Option<String>> {
-    let members = get_member_list(endpoint).await?;
+pub async fn get_my_member_id(
+    client: &EtcdClient,
+    endpoint: &str,
+    my_name: &str,
+) -> Result<Option<u64>> {
+    let members = get_member_list(client, endpoint).await?;
     for member in members {
         if member.name == my_name {
             return Ok(Some(member.id));
@@ -95,8 +81,8 @@ pub async fn get_my_member_id(endpoint: &str, my_name: &str) -> Result<Option<St
 
 /// Check if this member is a learner
 /// Returns Err if we can't determine state
-pub async fn is_learner(endpoint: &str, my_name: &str) -> Result<bool> {
-    let members = get_member_list(endpoint).await?;
+pub async fn is_learner(client: &EtcdClient, endpoint: &str, my_name: &str) -> Result<bool> {
+    let members = get_member_list(client, endpoint).await?;
     for member in members {
         if member.name == my_name {
             return Ok(member.is_learner);
@@ -108,6 +94,7 @@ pub async fn is_learner(endpoint: &str, my_name: &str) -> Result<bool> {
 
 /// Remove stale member entry for this node
 pub async fn remove_stale_self(
+    client: &EtcdClient,
     endpoint: &str,
     my_name: &str,
     my_peer_url: &str,
@@ -115,23 +102,16 @@ pub async fn remove_stale_self(
 ) -> Result<()> {
     info!("Checking for stale member entry...");
 
-    let members = get_member_list(endpoint).await?;
+    let members = get_member_list(client, endpoint).await?;
 
     for member in members {
-        if member.name == my_name || member.peer_url == my_peer_url {
-            info!(id = %member.id, "Removing stale member entry");
-            match etcdctl(&[
-                "member",
-                "remove",
-                &member.id,
-                &format!("--endpoints={}", endpoint),
-            ])
-            .await
-            {
-                Ok(_) => {
+        if member.name == my_name || member.peer_urls.iter().any(|u| u == my_peer_url) {
+            info!(id = member.id, "Removing stale member entry");
+            match client.member_remove(endpoint, member.id).await {
+                Ok(()) => {
                     telemetry.send(TelemetryEvent::EtcdStaleMemberRemoved {
                         node: my_name.to_string(),
-                        removed_id: member.id.clone(),
+                        removed_id: member.id.to_string(),
                     });
                     info!("Stale member removed");
                     return Ok(());
@@ -154,16 +134,17 @@ pub async fn remove_stale_self(
 
 /// Build current cluster membership for joining node
 pub async fn get_current_cluster(
+    client: &EtcdClient,
     endpoint: &str,
     my_name: &str,
     my_peer_url: &str,
 ) -> Result<String> {
-    let members = get_member_list(endpoint).await?;
+    let members = get_member_list(client, endpoint).await?;
 
     let mut cluster_parts: Vec<String> = members
         .iter()
-        .filter(|m| !m.name.is_empty() && !m.peer_url.is_empty())
-        .map(|m| format!("{}={}", m.name, m.peer_url))
+        .filter(|m| !m.name.is_empty())
+        .filter_map(|m| m.peer_urls.first().map(|url| format!("{}={}", m.name, url)))
         .collect();
 
     if !cluster_parts
@@ -189,9 +170,9 @@ pub async fn add_self_to_cluster(
     info!(node = %config.etcd_name, via = %leader_endpoint, "Adding self as learner");
 
     // Check if already a member
-    let members = get_member_list(leader_endpoint).await?;
+    let members = get_member_list(&config.etcd_client, leader_endpoint).await?;
     for member in &members {
-        if member.name == config.etcd_name || member.peer_url == my_peer_url {
+        if member.name == config.etcd_name || member.peer_urls.iter().any(|u| u == &my_peer_url) {
             // Fail-safe: only remove if we're SURE there's no local data
             let has_data = match has_local_data(&config.data_dir).await {
                 Ok(has) => has,
@@ -204,7 +185,8 @@ pub async fn add_self_to_cluster(
 
             if !has_data {
                 warn!("Registered as member but no local data - removing stale entry");
-                remove_stale_self(leader_endpoint, &config.etcd_name, &my_peer_url, telemetry).await?;
+                remove_stale_self(&config.etcd_client, leader_endpoint, &config.etcd_name, &my_peer_url, telemetry)
+                    .await?;
 
                 // Clean partial data
                 match clear_directory(Path::new(&config.data_dir)).await {
@@ -225,23 +207,21 @@ pub async fn add_self_to_cluster(
                 break;
             } else {
                 info!("Already a member with local data");
-                return get_current_cluster(leader_endpoint, &config.etcd_name, &my_peer_url).await;
+                return get_current_cluster(&config.etcd_client, leader_endpoint, &config.etcd_name, &my_peer_url)
+                    .await;
             }
         }
     }
 
-    // Add as learner
-    let output = match etcdctl(&[
-        "member",
-        "add",
-        &config.etcd_name,
-        "--learner",
-        &format!("--peer-urls={}", my_peer_url),
-        &format!("--endpoints={}", leader_endpoint),
-    ])
-    .await
+    // Add as learner. The RPC response already carries the full member list,
+    // so the resulting cluster string is built directly from it instead of
+    // scraping an `ETCD_INITIAL_CLUSTER=` line out of CLI output.
+    let members = match config
+        .etcd_client
+        .member_add_as_learner(leader_endpoint, &my_peer_url)
+        .await
     {
-        Ok(output) => output,
+        Ok(members) => members,
         Err(e) => {
             telemetry.send(TelemetryEvent::ComponentError {
                 component: "etcd".to_string(),
@@ -254,41 +234,61 @@ pub async fn add_self_to_cluster(
 
     info!(via = %leader, "Successfully added as learner");
 
-    // Extract ETCD_INITIAL_CLUSTER from output
-    for line in output.lines() {
-        if line.contains("ETCD_INITIAL_CLUSTER=") {
-            let cluster = line
-                .split("ETCD_INITIAL_CLUSTER=")
-                .nth(1)
-                .map(|s| s.trim_matches('"').to_string());
-            if let Some(c) = cluster {
-                if !c.is_empty() {
-                    return Ok(c);
-                }
-            }
-        }
+    // The newly-added member has no name yet (etcd only assigns one once the
+    // member itself starts and registers), so it's filtered out here and
+    // appended explicitly below, same as `get_current_cluster` does.
+    let mut cluster_parts: Vec<String> = members
+        .iter()
+        .filter(|m| !m.name.is_empty())
+        .filter_map(|m| m.peer_urls.first().map(|url| format!("{}={}", m.name, url)))
+        .collect();
+
+    if !cluster_parts
+        .iter()
+        .any(|p| p.starts_with(&format!("{}=", config.etcd_name)))
+    {
+        cluster_parts.push(format!("{}={}", config.etcd_name, my_peer_url));
     }
 
-    info!("Extracting cluster from member list");
-    get_current_cluster(leader_endpoint, &config.etcd_name, &my_peer_url).await
+    Ok(cluster_parts.join(","))
 }
 
-/// Promote self from learner to voting member
-pub async fn promote_self(
-    initial_cluster: &str,
-    my_name: &str,
-    telemetry: &Telemetry,
-) -> Result<()> {
-    let endpoint = get_voting_member_endpoint(initial_cluster)
+/// How close this member's raft applied index must be to the leader's
+/// before a promote attempt is likely to succeed - etcd's own promotion
+/// safety check rejects learners that haven't caught up.
+const CATCH_UP_THRESHOLD: u64 = 500;
+
+/// Outcome of a single `promote_self` attempt.
+pub enum PromotionOutcome {
+    /// Successfully promoted to voting member.
+    Promoted,
+    /// Already a voting member - nothing to do.
+    AlreadyVoting,
+    /// Not yet safe to promote (still catching up, or etcd rejected with a
+    /// transient "not ready"/"would lose quorum" error). Retry later.
+    NotReady,
+    /// Caught up, but placement says promoting now would risk quorum
+    /// safety (see `placement`). Retry later.
+    Deferred,
+}
+
+/// Attempt to promote self from learner to voting member. Only promotes
+/// once this member's raft applied index is within `CATCH_UP_THRESHOLD` of
+/// the leader's, and only if doing so keeps voting membership safe against
+/// a single-zone outage (see `placement`).
+pub async fn promote_self(config: &Config, telemetry: &Telemetry) -> Result<PromotionOutcome> {
+    let my_name = &config.etcd_name;
+    let client = &config.etcd_client;
+    let endpoint = get_voting_member_endpoint(client, &config.initial_cluster)
         .await?
         .ok_or_else(|| anyhow!("Could not find voting member endpoint"))?;
 
-    let member_id = get_my_member_id(&endpoint, my_name)
+    let member_id = get_my_member_id(client, &endpoint, my_name)
         .await?
         .ok_or_else(|| anyhow!("Could not find my member ID"))?;
 
     // Fail-safe: if we can't determine learner status, don't attempt promotion
-    let learner = match is_learner(&endpoint, my_name).await {
+    let learner = match is_learner(client, &endpoint, my_name).await {
         Ok(is) => is,
         Err(e) => {
             warn!(error = %e, "Can't determine learner status, skipping promotion");
@@ -298,34 +298,49 @@ pub async fn promote_self(
 
     if !learner {
         info!("Already a voting member");
-        return Ok(());
+        return Ok(PromotionOutcome::AlreadyVoting);
     }
 
-    info!(id = %member_id, via = %endpoint, "Promoting from learner to voting member");
+    let local_index = client.applied_index("http://127.0.0.1:2379").await?;
+    let leader_index = client.applied_index(&endpoint).await?;
+    let gap = leader_index.saturating_sub(local_index);
 
-    match etcdctl(&[
-        "member",
-        "promote",
-        &member_id,
-        &format!("--endpoints={}", endpoint),
-    ])
-    .await
-    {
-        Ok(_) => {
+    if gap > CATCH_UP_THRESHOLD {
+        info!(gap, threshold = CATCH_UP_THRESHOLD, "Still catching up to leader, deferring promotion");
+        return Ok(PromotionOutcome::NotReady);
+    }
+
+    let members = get_member_list(client, &endpoint).await?;
+    let datacenters = placement::get_datacenters(client, &endpoint).await?;
+    let decision = placement::evaluate_promotion(&members, &datacenters, my_name);
+    placement::log_decision(my_name, &decision);
+
+    if !decision.should_promote {
+        return Ok(PromotionOutcome::Deferred);
+    }
+
+    info!(id = member_id, via = %endpoint, gap, "Promoting from learner to voting member");
+
+    match client.member_promote(&endpoint, member_id).await {
+        Ok(()) => {
             info!("Promoted to voting member");
             telemetry.send(TelemetryEvent::EtcdNodePromoted {
                 node: my_name.to_string(),
             });
-            Ok(())
+            Ok(PromotionOutcome::Promoted)
         }
         Err(e) => {
-            if e.to_string().contains("is not a learner") {
+            let msg = e.to_string();
+            if msg.contains("is not a learner") {
                 info!("Already a voting member");
-                Ok(())
+                Ok(PromotionOutcome::AlreadyVoting)
+            } else if msg.contains("not ready") || msg.contains("would lose quorum") {
+                warn!(error = %msg, "Promotion not ready yet, backing off");
+                Ok(PromotionOutcome::NotReady)
             } else {
                 telemetry.send(TelemetryEvent::ComponentError {
                     component: "etcd".to_string(),
-                    error: e.to_string(),
+                    error: msg,
                     context: format!("promoting {} to voting member", my_name),
                 });
                 Err(e)
@@ -377,16 +392,19 @@ pub async fn clear_directory(path: &Path) -> Result<()> {
 pub async fn start_etcd(
     initial_cluster: &str,
     initial_cluster_state: &str,
+    db_quota_bytes: u64,
 ) -> Result<tokio::process::Child> {
     info!(
         cluster = %initial_cluster,
         state = %initial_cluster_state,
+        db_quota_bytes,
         "Starting etcd"
     );
 
     let child = Command::new("/usr/local/bin/etcd")
         .arg("--auto-compaction-retention=1")
         .arg("--max-learners=2")
+        .arg(format!("--quota-backend-bytes={}", db_quota_bytes))
         .env("ETCD_INITIAL_CLUSTER", initial_cluster)
         .env("ETCD_INITIAL_CLUSTER_STATE", initial_cluster_state)
         .stdin(Stdio::null())
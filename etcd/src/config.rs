@@ -3,7 +3,7 @@
 //! Handles environment variable parsing and validation.
 
 use anyhow::{anyhow, Result};
-use common::ConfigExt;
+use common::{ConfigExt, EtcdClient};
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -12,23 +12,44 @@ pub struct Config {
     pub data_dir: String,
     pub max_retries: u32,
     pub retry_delay: Duration,
+    pub max_retry_delay: Duration,
     pub peer_wait_timeout: Duration,
     pub peer_check_interval: Duration,
     pub etcd_name: String,
     pub initial_cluster: String,
+    pub datacenter: String,
+    pub shutdown_grace_period: Duration,
+    pub defrag_interval: Duration,
+    pub db_quota_bytes: u64,
+    /// Shared client so repeated calls against the same endpoint (health
+    /// probes, member lookups) reuse one gRPC connection and health-cache
+    /// entry instead of each caller dialing and probing from scratch.
+    pub etcd_client: EtcdClient,
 }
 
 impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        let initial_cluster = String::env_required("ETCD_INITIAL_CLUSTER")?;
+        let endpoints = parse_initial_cluster(&initial_cluster)?
+            .into_values()
+            .map(|peer_url| peer_to_client_url(&peer_url))
+            .collect();
+
         Ok(Self {
             data_dir: String::env_or("ETCD_DATA_DIR", "/var/lib/etcd"),
             max_retries: u32::env_parse("ETCD_MAX_RETRIES", 60),
             retry_delay: Duration::from_secs(u64::env_parse("ETCD_RETRY_DELAY", 5)),
+            max_retry_delay: Duration::from_secs(u64::env_parse("ETCD_MAX_RETRY_DELAY", 60)),
             peer_wait_timeout: Duration::from_secs(u64::env_parse("ETCD_PEER_WAIT_TIMEOUT", 300)),
             peer_check_interval: Duration::from_secs(u64::env_parse("ETCD_PEER_CHECK_INTERVAL", 5)),
             etcd_name: String::env_required("ETCD_NAME")?,
-            initial_cluster: String::env_required("ETCD_INITIAL_CLUSTER")?,
+            datacenter: String::env_or("ETCD_DATACENTER", "default"),
+            shutdown_grace_period: Duration::from_secs(u64::env_parse("ETCD_SHUTDOWN_GRACE_PERIOD", 30)),
+            defrag_interval: Duration::from_secs(u64::env_parse("ETCD_DEFRAG_INTERVAL", 6 * 3600)),
+            db_quota_bytes: u64::env_parse("ETCD_DB_QUOTA", 8 * 1024 * 1024 * 1024),
+            etcd_client: EtcdClient::new(endpoints),
+            initial_cluster,
         })
     }
 
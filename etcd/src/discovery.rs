@@ -0,0 +1,196 @@
+//! Dynamic peer discovery
+//!
+//! `ETCD_INITIAL_CLUSTER` freezes membership at startup, so scaling the
+//! cluster or replacing a node's address requires an env change and
+//! restart. This module instead polls a pluggable `DiscoverySource` (static
+//! env, Consul service catalog, or DNS SRV records) on a background
+//! interval and persists the last-known-good peer map to
+//! `{data_dir}/peers.json`, so a node that restarts with an unreachable
+//! configured leader can still try previously-seen peers. When the
+//! discovered peer map changes, newly-seen peers that aren't yet etcd
+//! members are reconciled in as learners.
+
+use crate::config::{parse_initial_cluster, Config};
+use anyhow::{Context, Result};
+use common::{EtcdClient, Telemetry, TelemetryEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where to source the live peer list from.
+#[derive(Debug, Clone)]
+pub enum DiscoverySource {
+    /// Fixed, from `ETCD_INITIAL_CLUSTER` - no network lookups.
+    Static,
+    /// Consul service catalog (`CONSUL_HTTP_ADDR` + `ETCD_CONSUL_SERVICE`).
+    Consul { http_addr: String, service: String },
+    /// DNS SRV records (`ETCD_DNS_SRV_NAME`).
+    DnsSrv { srv_name: String },
+}
+
+impl DiscoverySource {
+    /// Pick a source from environment variables, defaulting to `Static`.
+    pub fn from_env() -> Self {
+        if let (Ok(http_addr), Ok(service)) = (
+            std::env::var("CONSUL_HTTP_ADDR"),
+            std::env::var("ETCD_CONSUL_SERVICE"),
+        ) {
+            return Self::Consul { http_addr, service };
+        }
+        if let Ok(srv_name) = std::env::var("ETCD_DNS_SRV_NAME") {
+            return Self::DnsSrv { srv_name };
+        }
+        Self::Static
+    }
+}
+
+/// Persisted peer map, written to `{data_dir}/peers.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedPeers {
+    peers: HashMap<String, String>,
+}
+
+fn peers_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join("peers.json")
+}
+
+/// Load the last-known-good peer map, if any was persisted. Missing or
+/// unreadable files are treated as "nothing known yet" rather than an error,
+/// since this is only ever used as a best-effort fallback.
+pub async fn load_persisted(data_dir: &str) -> HashMap<String, String> {
+    match tokio::fs::read_to_string(peers_path(data_dir)).await {
+        Ok(contents) => serde_json::from_str::<PersistedPeers>(&contents)
+            .map(|p| p.peers)
+            .unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+async fn persist(data_dir: &str, peers: &HashMap<String, String>) -> Result<()> {
+    let doc = PersistedPeers { peers: peers.clone() };
+    let json = serde_json::to_string_pretty(&doc).context("Failed to serialize peers.json")?;
+    tokio::fs::write(peers_path(data_dir), json)
+        .await
+        .context("Failed to write peers.json")
+}
+
+async fn query(source: &DiscoverySource, initial_cluster: &str) -> Result<HashMap<String, String>> {
+    match source {
+        DiscoverySource::Static => parse_initial_cluster(initial_cluster),
+        DiscoverySource::Consul { http_addr, service } => query_consul(http_addr, service).await,
+        DiscoverySource::DnsSrv { srv_name } => query_dns_srv(srv_name).await,
+    }
+}
+
+#[derive(Deserialize)]
+struct ConsulServiceEntry {
+    #[serde(rename = "Node")]
+    node: String,
+    #[serde(rename = "ServiceAddress")]
+    service_address: String,
+    #[serde(rename = "ServicePort")]
+    service_port: u16,
+}
+
+async fn query_consul(http_addr: &str, service: &str) -> Result<HashMap<String, String>> {
+    let url = format!("{http_addr}/v1/catalog/service/{service}");
+    let entries: Vec<ConsulServiceEntry> = reqwest::get(&url)
+        .await
+        .context("Consul catalog request failed")?
+        .json()
+        .await
+        .context("Failed to parse Consul catalog response")?;
+
+    Ok(entries
+        .into_iter()
+        .map(|e| {
+            let peer_url = format!("http://{}:{}", e.service_address, e.service_port);
+            (e.node, peer_url)
+        })
+        .collect())
+}
+
+async fn query_dns_srv(srv_name: &str) -> Result<HashMap<String, String>> {
+    use hickory_resolver::TokioAsyncResolver;
+
+    let resolver =
+        TokioAsyncResolver::tokio_from_system_conf().context("Failed to build DNS resolver")?;
+    let response = resolver.srv_lookup(srv_name).await.context("SRV lookup failed")?;
+
+    Ok(response
+        .iter()
+        .map(|srv| {
+            let target = srv.target().to_string().trim_end_matches('.').to_string();
+            let peer_url = format!("http://{}:{}", target, srv.port());
+            (target, peer_url)
+        })
+        .collect())
+}
+
+/// Reconcile discovered peers against current etcd membership: any name
+/// present in `peers` but missing from the member list is added as a
+/// learner, enabling membership to grow beyond the original bootstrap set.
+async fn reconcile_membership(
+    client: &EtcdClient,
+    endpoint: &str,
+    peers: &HashMap<String, String>,
+    telemetry: &Telemetry,
+) -> Result<()> {
+    let members = crate::cluster::get_member_list(client, endpoint).await?;
+    let known: HashSet<&str> = members.iter().map(|m| m.name.as_str()).collect();
+
+    for (name, peer_url) in peers {
+        if known.contains(name.as_str()) {
+            continue;
+        }
+
+        info!(node = %name, peer_url = %peer_url, "Discovered peer not yet a member, adding as learner");
+        match client.member_add_as_learner(endpoint, peer_url).await {
+            Ok(_) => {
+                telemetry.send(TelemetryEvent::EtcdNodeJoined {
+                    node: name.clone(),
+                    joined_as: "learner".to_string(),
+                });
+            }
+            Err(e) => {
+                warn!(node = %name, error = %e, "Failed to add discovered peer as learner");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the discovery loop forever: poll `source`, persist the peer map when
+/// it changes, and reconcile any newly-discovered peers into membership.
+pub async fn run(config: Config, source: DiscoverySource, telemetry: Telemetry) {
+    let mut last: HashMap<String, String> = load_persisted(&config.data_dir).await;
+
+    loop {
+        sleep(DISCOVERY_INTERVAL).await;
+
+        match query(&source, &config.initial_cluster).await {
+            Ok(current) if current != last => {
+                info!(count = current.len(), "Discovered peer list changed, persisting");
+                if let Err(e) = persist(&config.data_dir, &current).await {
+                    warn!(error = %e, "Failed to persist discovered peers");
+                }
+
+                if let Err(e) =
+                    reconcile_membership(&config.etcd_client, "http://127.0.0.1:2379", &current, &telemetry).await
+                {
+                    debug!(error = %e, "Peer reconciliation skipped (local etcd not up yet?)");
+                }
+
+                last = current;
+            }
+            Ok(_) => debug!("Discovered peer list unchanged"),
+            Err(e) => warn!(error = %e, "Peer discovery query failed"),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Lease-backed bootstrap leader election
+//!
+//! `get_bootstrap_leader` picks the alphabetically first node name, which is
+//! static and can't adapt if that node is permanently gone or slow - the
+//! whole cluster can stall waiting on a name that will never come up.
+//! Instead, once any peer endpoint is reachable, every node races a
+//! lease-backed transactional put on a well-known key; the winner becomes
+//! leader and proceeds to single-node bootstrap, losers join as learners
+//! against the winner. If the leader's lease expires (crash), the key is
+//! auto-deleted and the next attempt re-runs the election. The alphabetical
+//! tiebreak is kept as a fallback for the true cold-start case, where no
+//! endpoint is reachable yet and there's nothing to race against.
+
+use crate::config::{parse_initial_cluster, peer_to_client_url, Config};
+use anyhow::{anyhow, Result};
+use common::EtcdClient;
+use tracing::{debug, info};
+
+const BOOTSTRAP_LEADER_KEY: &str = "/postgres-ha/bootstrap-leader";
+const LEASE_TTL_SECS: i64 = 10;
+
+/// Holds the bootstrap-leader lease for as long as it lives. Dropping it
+/// stops the keep-alive task and revokes the lease in the background so the
+/// key is reclaimed immediately (e.g. on a failed bootstrap attempt) instead
+/// of leaving the next election to wait out the lease's TTL.
+pub struct LeaderGuard {
+    endpoint: String,
+    lease_id: i64,
+    keep_alive: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for LeaderGuard {
+    fn drop(&mut self) {
+        if let Some(handle) = self.keep_alive.take() {
+            handle.abort();
+        }
+
+        let endpoint = self.endpoint.clone();
+        let lease_id = self.lease_id;
+        tokio::spawn(async move {
+            let client = EtcdClient::new(vec![]);
+            if let Err(e) = client.revoke_lease(&endpoint, lease_id).await {
+                debug!(error = %e, "Failed to revoke bootstrap-leader lease on drop");
+            }
+        });
+    }
+}
+
+/// Outcome of one election attempt.
+pub struct Election {
+    pub leader_name: String,
+    pub is_leader: bool,
+    /// Present only when this node won: holding it keeps the claim alive,
+    /// dropping it releases it.
+    pub guard: Option<LeaderGuard>,
+}
+
+/// Attempt to elect a bootstrap leader via etcd. Returns `None` if no peer
+/// endpoint is reachable yet, signaling the caller should fall back to the
+/// static alphabetical tiebreak.
+pub async fn elect_leader(config: &Config) -> Result<Option<Election>> {
+    let cluster = parse_initial_cluster(&config.initial_cluster)?;
+    let client = &config.etcd_client;
+
+    let candidates: Vec<String> = cluster.values().map(|url| peer_to_client_url(url)).collect();
+    let Some(endpoint) = client.first_healthy(&candidates).await else {
+        return Ok(None);
+    };
+
+    if !cluster.contains_key(&config.etcd_name) {
+        return Err(anyhow!("{} not present in ETCD_INITIAL_CLUSTER", config.etcd_name));
+    }
+
+    let lease_id = client.grant_lease(&endpoint, LEASE_TTL_SECS).await?;
+
+    if client
+        .try_claim(&endpoint, BOOTSTRAP_LEADER_KEY, &config.etcd_name, lease_id)
+        .await?
+    {
+        info!(node = %config.etcd_name, "Won bootstrap leader election");
+        let keep_alive = client.keep_lease_alive(&endpoint, lease_id);
+        return Ok(Some(Election {
+            leader_name: config.etcd_name.clone(),
+            is_leader: true,
+            guard: Some(LeaderGuard {
+                endpoint,
+                lease_id,
+                keep_alive: Some(keep_alive),
+            }),
+        }));
+    }
+
+    // This lease didn't end up attached to anything - reclaim it now rather
+    // than leaving it to expire on its own TTL.
+    if let Err(e) = client.revoke_lease(&endpoint, lease_id).await {
+        debug!(error = %e, "Failed to revoke unused election lease");
+    }
+
+    let leader_name = client
+        .get(&endpoint, BOOTSTRAP_LEADER_KEY)
+        .await?
+        .ok_or_else(|| anyhow!("Lost election but bootstrap-leader key is missing"))?;
+
+    info!(leader = %leader_name, "Lost bootstrap leader election, joining as learner");
+    Ok(Some(Election {
+        is_leader: leader_name == config.etcd_name,
+        leader_name,
+        guard: None,
+    }))
+}
@@ -0,0 +1,132 @@
+//! Phi-accrual failure detection for peer health
+//!
+//! A single fixed-interval health probe is noisy: one slow response flips a
+//! peer from "up" to "down" even though it's likely just a transient
+//! latency spike. This instead tracks, per peer, a bounded sliding window
+//! of the intervals between successful probes, fits a normal distribution
+//! to that window, and estimates phi = -log10(P(time since last heartbeat))
+//! at query time. A peer is only considered down once phi crosses a
+//! threshold, so suspicion accrues gradually instead of flipping on one
+//! missed check.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW_SIZE: usize = 20;
+const DEFAULT_PHI_THRESHOLD: f64 = 8.0;
+
+struct HeartbeatHistory {
+    intervals: Vec<f64>,
+    last_heartbeat: Instant,
+}
+
+impl HeartbeatHistory {
+    /// Seed with two samples of `peer_check_interval`, so phi is computable
+    /// from the first real heartbeat instead of needing a full window first.
+    fn seeded(peer_check_interval: Duration) -> Self {
+        let seed = peer_check_interval.as_secs_f64().max(0.001);
+        Self {
+            intervals: vec![seed, seed],
+            last_heartbeat: Instant::now(),
+        }
+    }
+
+    fn record_heartbeat(&mut self) {
+        let now = Instant::now();
+        let gap = now.duration_since(self.last_heartbeat).as_secs_f64();
+        self.intervals.push(gap);
+        if self.intervals.len() > WINDOW_SIZE {
+            self.intervals.remove(0);
+        }
+        self.last_heartbeat = now;
+    }
+
+    fn mean(&self) -> f64 {
+        self.intervals.iter().sum::<f64>() / self.intervals.len() as f64
+    }
+
+    fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let variance =
+            self.intervals.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / self.intervals.len() as f64;
+        // Floor so a window of identical samples doesn't divide by zero.
+        variance.max(1e-6).sqrt()
+    }
+
+    /// phi = -log10(P(gap or later)) for the time elapsed since the last
+    /// heartbeat, under a normal distribution fit to `intervals`.
+    fn phi(&self) -> f64 {
+        let gap = Instant::now().duration_since(self.last_heartbeat).as_secs_f64();
+        let y = (gap - self.mean()) / (self.std_dev() * std::f64::consts::SQRT_2);
+        let p_later = 0.5 * erfc(y);
+        if p_later <= f64::MIN_POSITIVE {
+            f64::INFINITY
+        } else {
+            -p_later.log10()
+        }
+    }
+}
+
+/// Complementary error function, approximated per Abramowitz & Stegun 7.1.26.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let tau = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 {
+        tau
+    } else {
+        2.0 - tau
+    }
+}
+
+/// Tracks per-peer heartbeat history and derives availability via
+/// phi-accrual rather than a single pass/fail probe.
+pub struct FailureDetector {
+    peer_check_interval: Duration,
+    threshold: f64,
+    histories: Mutex<HashMap<String, HeartbeatHistory>>,
+}
+
+impl FailureDetector {
+    pub fn new(peer_check_interval: Duration) -> Self {
+        Self {
+            peer_check_interval,
+            threshold: DEFAULT_PHI_THRESHOLD,
+            histories: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Record a successful probe of `peer`.
+    pub fn report_heartbeat(&self, peer: &str) {
+        let mut histories = self.histories.lock().unwrap();
+        histories
+            .entry(peer.to_string())
+            .and_modify(|h| h.record_heartbeat())
+            .or_insert_with(|| HeartbeatHistory::seeded(self.peer_check_interval));
+    }
+
+    /// Current phi for `peer`, or `0.0` if we've never heard from it (treated
+    /// as available until a probe says otherwise).
+    pub fn phi(&self, peer: &str) -> f64 {
+        self.histories.lock().unwrap().get(peer).map(|h| h.phi()).unwrap_or(0.0)
+    }
+
+    /// Whether `peer` should be treated as available right now.
+    pub fn is_available(&self, peer: &str) -> bool {
+        self.phi(peer) < self.threshold
+    }
+}
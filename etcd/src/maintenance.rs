@@ -0,0 +1,70 @@
+//! Periodic defragmentation and NOSPACE alarm handling
+//!
+//! `--auto-compaction-retention` only marks old revisions as free internally
+//! - the backing bbolt file never shrinks on its own, so a long-running
+//! cluster can still trip the NOSPACE alarm and wedge itself into read-only
+//! mode even with compaction enabled. This worker defragments one voting
+//! member at a time (never the whole cluster at once - defrag blocks that
+//! member's writes for its duration) on a configurable interval, and clears
+//! any NOSPACE alarm it finds once the defrag has freed space.
+
+use crate::cluster::get_voting_member_endpoint;
+use crate::config::Config;
+use crate::worker::{Worker, WorkerState};
+use anyhow::Result;
+use common::{Telemetry, TelemetryEvent};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub struct MaintenanceWorker {
+    config: Config,
+    telemetry: Telemetry,
+}
+
+impl MaintenanceWorker {
+    pub fn new(config: Config, telemetry: Telemetry) -> Self {
+        Self { config, telemetry }
+    }
+}
+
+impl Worker for MaintenanceWorker {
+    fn name(&self) -> &str {
+        "etcd-maintenance"
+    }
+
+    fn interval(&self) -> Duration {
+        self.config.defrag_interval
+    }
+
+    async fn step(&mut self) -> Result<WorkerState> {
+        let client = &self.config.etcd_client;
+
+        let Some(endpoint) = get_voting_member_endpoint(client, &self.config.initial_cluster).await? else {
+            return Ok(WorkerState::Idle);
+        };
+
+        let had_alarm = client.has_nospace_alarm(&endpoint).await.unwrap_or(false);
+        if had_alarm {
+            warn!(endpoint = %endpoint, "NOSPACE alarm active, defragmenting before disarming");
+        }
+
+        let before = client.db_size(&endpoint).await.ok();
+
+        info!(endpoint = %endpoint, db_size_before = ?before, "Defragmenting etcd member");
+        client.defragment(&endpoint).await?;
+
+        let after = client.db_size(&endpoint).await.ok();
+        info!(endpoint = %endpoint, db_size_before = ?before, db_size_after = ?after, "Defragmentation complete");
+
+        if had_alarm {
+            client.disarm_nospace_alarm(&endpoint).await?;
+            info!(endpoint = %endpoint, "NOSPACE alarm disarmed");
+            self.telemetry.send(TelemetryEvent::EtcdAlarmCleared {
+                node: self.config.etcd_name.clone(),
+                alarm: "NOSPACE".to_string(),
+            });
+        }
+
+        Ok(WorkerState::Idle)
+    }
+}
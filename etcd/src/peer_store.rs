@@ -0,0 +1,117 @@
+//! Persisted peer membership
+//!
+//! `wait_for_any_healthy_peer` re-discovers cluster membership from scratch
+//! on every (re)start, so a node restarting while the configured
+//! `bootstrap_leader` is down just retries discovery forever. This persists
+//! the last-known healthy peer URLs and their etcd member IDs to
+//! `{data_dir}/peer_store.json`, written atomically (temp file + rename) by
+//! a background `PeerPersisterWorker`, so a restarting node can try
+//! previously-healthy peers before falling back to full discovery.
+
+use crate::cluster::{get_member_list, get_voting_member_endpoint};
+use crate::config::Config;
+use crate::worker::{Worker, WorkerState};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A peer as last seen in the member list: its peer URL and (if it was a
+/// voting member at the time) its etcd member ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerRecord {
+    pub peer_url: String,
+    pub member_id: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedStore {
+    peers: HashMap<String, PeerRecord>,
+}
+
+fn store_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join("peer_store.json")
+}
+
+/// Load the last-persisted peer set, if any. Missing or unreadable files are
+/// treated as "nothing known yet" rather than an error, since this is only
+/// ever used as a best-effort fallback ahead of full discovery.
+pub async fn load(data_dir: &str) -> HashMap<String, PeerRecord> {
+    match tokio::fs::read_to_string(store_path(data_dir)).await {
+        Ok(contents) => serde_json::from_str::<PersistedStore>(&contents)
+            .map(|s| s.peers)
+            .unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Atomically persist `peers` via temp-file + rename, so a crash mid-write
+/// never leaves a truncated or corrupt file behind.
+async fn save(data_dir: &str, peers: &HashMap<String, PeerRecord>) -> Result<()> {
+    let path = store_path(data_dir);
+    let tmp_path = path.with_extension("json.tmp");
+    let doc = PersistedStore { peers: peers.clone() };
+    let json = serde_json::to_string_pretty(&doc).context("Failed to serialize peer_store.json")?;
+
+    tokio::fs::write(&tmp_path, json)
+        .await
+        .context("Failed to write peer_store temp file")?;
+    tokio::fs::rename(&tmp_path, &path)
+        .await
+        .context("Failed to rename peer_store temp file into place")?;
+    Ok(())
+}
+
+/// Background worker that periodically refreshes `peer_store.json` from the
+/// live member list while this node is up.
+pub struct PeerPersisterWorker {
+    config: Config,
+}
+
+impl PeerPersisterWorker {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl Worker for PeerPersisterWorker {
+    fn name(&self) -> &str {
+        "peer-persister"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    async fn step(&mut self) -> Result<WorkerState> {
+        let Some(endpoint) = get_voting_member_endpoint(&self.config.etcd_client, &self.config.initial_cluster).await?
+        else {
+            return Ok(WorkerState::Idle);
+        };
+
+        let members = get_member_list(&self.config.etcd_client, &endpoint).await?;
+        let peers: HashMap<String, PeerRecord> = members
+            .into_iter()
+            .filter(|m| !m.name.is_empty())
+            .filter_map(|m| {
+                m.peer_urls.first().map(|url| {
+                    (
+                        m.name.clone(),
+                        PeerRecord {
+                            peer_url: url.clone(),
+                            member_id: Some(m.id),
+                        },
+                    )
+                })
+            })
+            .collect();
+
+        if peers.is_empty() {
+            return Ok(WorkerState::Idle);
+        }
+
+        save(&self.config.data_dir, &peers).await?;
+        Ok(WorkerState::Idle)
+    }
+}
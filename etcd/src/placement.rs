@@ -0,0 +1,82 @@
+//! Failure-domain aware promotion placement
+//!
+//! Each node's datacenter/zone (`ETCD_DATACENTER`) is registered in etcd
+//! under a well-known key prefix so it can be looked up alongside the
+//! regular member list. `evaluate_promotion` uses that to decide whether
+//! promoting a learner to a voting member is safe: a zone that already
+//! holds (or would hold) a majority of voters on its own is one outage away
+//! from breaking quorum for the whole cluster, so promotion into an
+//! already-dominant zone is deferred rather than applied blindly.
+
+use anyhow::Result;
+use common::{EtcdClient, EtcdMemberInfo as MemberInfo};
+use std::collections::HashMap;
+use tracing::info;
+
+const DATACENTER_KEY_PREFIX: &str = "/postgres-ha/datacenter/";
+
+/// Record this node's datacenter in etcd. Safe to call repeatedly.
+pub async fn register_datacenter(client: &EtcdClient, endpoint: &str, name: &str, datacenter: &str) -> Result<()> {
+    client
+        .put(endpoint, &format!("{}{}", DATACENTER_KEY_PREFIX, name), datacenter)
+        .await
+}
+
+/// Look up every registered node's datacenter, keyed by node name.
+pub async fn get_datacenters(client: &EtcdClient, endpoint: &str) -> Result<HashMap<String, String>> {
+    client.get_prefix(endpoint, DATACENTER_KEY_PREFIX).await
+}
+
+/// Outcome of evaluating whether to promote `candidate` right now.
+pub struct PlacementDecision {
+    pub should_promote: bool,
+    pub reason: String,
+}
+
+fn zone_of(datacenters: &HashMap<String, String>, name: &str) -> String {
+    datacenters.get(name).cloned().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Decide whether promoting `candidate` keeps voting membership safe against
+/// a single-zone outage: it's safe as long as no zone holds (or would hold,
+/// post-promotion) a majority of voters on its own.
+pub fn evaluate_promotion(
+    members: &[MemberInfo],
+    datacenters: &HashMap<String, String>,
+    candidate: &str,
+) -> PlacementDecision {
+    let voters: Vec<&MemberInfo> = members.iter().filter(|m| !m.is_learner).collect();
+    let total_after = voters.len() + 1;
+    let majority = total_after / 2 + 1;
+
+    let candidate_zone = zone_of(datacenters, candidate);
+    let mut zone_counts: HashMap<String, usize> = HashMap::new();
+    for voter in &voters {
+        *zone_counts.entry(zone_of(datacenters, &voter.name)).or_insert(0) += 1;
+    }
+
+    let candidate_zone_after = zone_counts.get(&candidate_zone).copied().unwrap_or(0) + 1;
+
+    if candidate_zone_after >= majority {
+        PlacementDecision {
+            should_promote: false,
+            reason: format!(
+                "zone {} would hold {}/{} voters (majority is {}) - deferring promotion to avoid a single-zone quorum risk",
+                candidate_zone, candidate_zone_after, total_after, majority
+            ),
+        }
+    } else {
+        PlacementDecision {
+            should_promote: true,
+            reason: format!(
+                "zone {} would hold {}/{} voters (majority is {}) - safe to promote",
+                candidate_zone, candidate_zone_after, total_after, majority
+            ),
+        }
+    }
+}
+
+/// Log the placement decision for `candidate` at the appropriate level.
+pub fn log_decision(candidate: &str, decision: &PlacementDecision) {
+    info!(node = %candidate, should_promote = decision.should_promote, reason = %decision.reason, "Promotion placement decision");
+}
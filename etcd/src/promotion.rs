@@ -0,0 +1,46 @@
+//! Drives `promote_self` until this learner becomes a voting member
+//!
+//! Wraps `cluster::promote_self` in a `Worker` so promotion is retried on
+//! its own schedule instead of piggy-backing on the bootstrap monitor: a
+//! learner that isn't caught up yet, or whose zone already holds a
+//! majority of voters, simply stays `Idle` and gets re-evaluated next step.
+
+use crate::cluster::{promote_self, PromotionOutcome};
+use crate::config::Config;
+use crate::worker::{Worker, WorkerState};
+use anyhow::Result;
+use common::Telemetry;
+use std::time::Duration;
+use tracing::info;
+
+pub struct PromotionWorker {
+    config: Config,
+    telemetry: Telemetry,
+}
+
+impl PromotionWorker {
+    pub fn new(config: Config, telemetry: Telemetry) -> Self {
+        Self { config, telemetry }
+    }
+}
+
+impl Worker for PromotionWorker {
+    fn name(&self) -> &str {
+        "learner-promotion"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    async fn step(&mut self) -> Result<WorkerState> {
+        match promote_self(&self.config, &self.telemetry).await? {
+            PromotionOutcome::Promoted => {
+                info!("Promotion worker done: now a voting member");
+                Ok(WorkerState::Done)
+            }
+            PromotionOutcome::AlreadyVoting => Ok(WorkerState::Done),
+            PromotionOutcome::NotReady | PromotionOutcome::Deferred => Ok(WorkerState::Idle),
+        }
+    }
+}
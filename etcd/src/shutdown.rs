@@ -0,0 +1,93 @@
+//! Graceful shutdown: signal handling, etcd termination, self-deregistration
+//!
+//! An orchestrator's SIGTERM reaching this process alone would otherwise be
+//! indistinguishable from an etcd crash (the retry loop would see a non-zero
+//! exit and, if bootstrap hadn't completed yet, wipe the data directory) and
+//! `Child::kill` sends SIGKILL on unix, giving etcd no chance to flush its
+//! WAL cleanly. Instead: on signal receipt, deregister this member (while
+//! etcd is still reachable to do so), then send etcd SIGTERM directly and
+//! wait up to a grace period before escalating to SIGKILL.
+
+use crate::cluster::get_my_member_id;
+use crate::config::Config;
+use anyhow::{Context, Result};
+use common::{Telemetry, TelemetryEvent};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::time::Duration;
+use tokio::process::Child;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+const LOCAL_ENDPOINT: &str = "http://127.0.0.1:2379";
+
+/// Wait until either SIGTERM or SIGINT is received.
+pub async fn wait_for_signal() -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate()).context("Failed to install SIGTERM handler")?;
+    let mut sigint = signal(SignalKind::interrupt()).context("Failed to install SIGINT handler")?;
+
+    tokio::select! {
+        _ = sigterm.recv() => info!("Received SIGTERM"),
+        _ = sigint.recv() => info!("Received SIGINT"),
+    }
+
+    Ok(())
+}
+
+/// If this node is still a cluster member, remove it so the remaining nodes
+/// don't carry a phantom peer once this process is gone. Must run before
+/// `stop_etcd` - once etcd exits there's nothing left to query or remove via.
+pub async fn deregister_self(config: &Config, telemetry: &Telemetry) -> Result<()> {
+    let member_id = match get_my_member_id(&config.etcd_client, LOCAL_ENDPOINT, &config.etcd_name).await {
+        Ok(id) => id,
+        Err(e) => {
+            warn!(error = %e, "Could not reach local etcd to check membership, skipping deregistration");
+            return Ok(());
+        }
+    };
+
+    let Some(member_id) = member_id else {
+        info!("Not a cluster member, nothing to deregister");
+        return Ok(());
+    };
+
+    match config.etcd_client.member_remove(LOCAL_ENDPOINT, member_id).await {
+        Ok(()) => {
+            info!(id = member_id, "Deregistered self from cluster");
+            telemetry.send(TelemetryEvent::EtcdGracefulShutdown {
+                node: config.etcd_name.clone(),
+                removed_id: member_id.to_string(),
+            });
+            Ok(())
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to deregister self from cluster");
+            Ok(())
+        }
+    }
+}
+
+/// Send etcd SIGTERM and wait up to `grace_period` for it to exit cleanly,
+/// escalating to SIGKILL if it doesn't.
+pub async fn stop_etcd(child: &mut Child, grace_period: Duration) -> Result<()> {
+    let Some(pid) = child.id() else {
+        return Ok(());
+    };
+
+    info!(pid, "Sending SIGTERM to etcd");
+    kill(Pid::from_raw(pid as i32), Signal::SIGTERM).context("Failed to signal etcd")?;
+
+    tokio::select! {
+        _ = child.wait() => {
+            info!("etcd exited cleanly after SIGTERM");
+        }
+        _ = sleep(grace_period) => {
+            warn!(grace_period = ?grace_period, "etcd did not exit in time, sending SIGKILL");
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+            let _ = child.wait().await;
+        }
+    }
+
+    Ok(())
+}
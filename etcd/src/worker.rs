@@ -0,0 +1,215 @@
+//! Background worker subsystem
+//!
+//! Replaces bare `tokio::spawn(...)` + `.abort()` for long-running
+//! supervisor tasks (bootstrap monitoring, peer persistence, promotion...).
+//! Aborting a task can cut it off mid-operation (e.g. partway through an
+//! etcd RPC), leaving no record of what it was doing. Instead each `Worker`
+//! is driven by its own loop in `WorkerManager`, reports a `WorkerState`
+//! after every step, and can be paused/resumed/cancelled cooperatively via
+//! a control channel - cancellation only takes effect between steps, never
+//! mid-step.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Outcome of a single worker step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerState {
+    /// Did useful work and wants to be stepped again immediately.
+    Active,
+    /// Nothing to do this step; back off for the worker's `interval()`.
+    Idle,
+    /// Finished permanently; the manager stops driving it.
+    Done,
+    /// Failed unrecoverably; the manager stops driving it.
+    Dead { error: String },
+}
+
+/// A background task driven by repeated calls to `step`.
+pub trait Worker: Send + 'static {
+    fn name(&self) -> &str;
+
+    /// How long to wait between steps after an `Idle` result.
+    fn interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    /// Perform one unit of work.
+    ///
+    /// Declared as `-> impl Future<...> + Send` rather than `async fn` so the
+    /// returned future has an explicit `Send` bound: `WorkerManager::spawn`
+    /// awaits this generically inside `tokio::spawn`, which requires the
+    /// whole async block to be `Send`, and a bare `async fn` in a trait
+    /// doesn't guarantee that for an arbitrary implementor.
+    fn step(&mut self) -> impl Future<Output = Result<WorkerState>> + Send;
+}
+
+/// Commands sent to a running worker's driving loop.
+enum Control {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+/// Snapshot of a worker's status for `list_workers()`.
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub state: String,
+    pub last_error: Option<String>,
+    pub iterations: u64,
+}
+
+struct WorkerHandle {
+    control: mpsc::Sender<Control>,
+    task: JoinHandle<()>,
+}
+
+/// Owns the registry of spawned workers and their status snapshots.
+#[derive(Clone)]
+pub struct WorkerManager {
+    statuses: Arc<Mutex<HashMap<String, WorkerStatus>>>,
+    handles: Arc<Mutex<HashMap<String, WorkerHandle>>>,
+}
+
+impl WorkerManager {
+    pub fn new() -> Self {
+        Self {
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+            handles: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawn `worker`, driving it in its own loop until it reports
+    /// `Done`/`Dead`, its step errors, or it's cancelled.
+    pub async fn spawn<W: Worker>(&self, mut worker: W) {
+        let name = worker.name().to_string();
+        let (control_tx, mut control_rx) = mpsc::channel(4);
+
+        self.statuses.lock().await.insert(
+            name.clone(),
+            WorkerStatus {
+                name: name.clone(),
+                state: "Active".to_string(),
+                last_error: None,
+                iterations: 0,
+            },
+        );
+
+        let statuses = self.statuses.clone();
+        let task_name = name.clone();
+        let task = tokio::spawn(async move {
+            let mut paused = false;
+
+            loop {
+                if paused {
+                    match control_rx.recv().await {
+                        Some(Control::Resume) => paused = false,
+                        Some(Control::Pause) => {}
+                        Some(Control::Cancel) | None => break,
+                    }
+                    continue;
+                }
+
+                let result = worker.step().await;
+
+                let mut next_wait = Duration::ZERO;
+                {
+                    let mut statuses = statuses.lock().await;
+                    let Some(status) = statuses.get_mut(&task_name) else {
+                        break;
+                    };
+                    status.iterations += 1;
+
+                    match result {
+                        Ok(WorkerState::Active) => {
+                            status.state = "Active".to_string();
+                            status.last_error = None;
+                        }
+                        Ok(WorkerState::Idle) => {
+                            status.state = "Idle".to_string();
+                            status.last_error = None;
+                            next_wait = worker.interval();
+                        }
+                        Ok(WorkerState::Done) => {
+                            status.state = "Done".to_string();
+                            status.last_error = None;
+                            drop(statuses);
+                            info!(worker = %task_name, "Worker done");
+                            break;
+                        }
+                        Ok(WorkerState::Dead { error }) => {
+                            status.state = "Dead".to_string();
+                            status.last_error = Some(error.clone());
+                            drop(statuses);
+                            warn!(worker = %task_name, error = %error, "Worker reported itself dead");
+                            break;
+                        }
+                        Err(e) => {
+                            status.state = "Dead".to_string();
+                            status.last_error = Some(e.to_string());
+                            drop(statuses);
+                            warn!(worker = %task_name, error = %e, "Worker step failed");
+                            break;
+                        }
+                    }
+                }
+
+                if next_wait.is_zero() {
+                    match control_rx.try_recv() {
+                        Ok(Control::Pause) => paused = true,
+                        Ok(Control::Resume) => {}
+                        Ok(Control::Cancel) | Err(mpsc::error::TryRecvError::Disconnected) => break,
+                        Err(mpsc::error::TryRecvError::Empty) => {}
+                    }
+                    continue;
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(next_wait) => {}
+                    cmd = control_rx.recv() => match cmd {
+                        Some(Control::Pause) => paused = true,
+                        Some(Control::Resume) => {}
+                        Some(Control::Cancel) | None => break,
+                    }
+                }
+            }
+        });
+
+        self.handles.lock().await.insert(name, WorkerHandle { control: control_tx, task });
+    }
+
+    pub async fn pause(&self, name: &str) {
+        if let Some(handle) = self.handles.lock().await.get(name) {
+            let _ = handle.control.send(Control::Pause).await;
+        }
+    }
+
+    pub async fn resume(&self, name: &str) {
+        if let Some(handle) = self.handles.lock().await.get(name) {
+            let _ = handle.control.send(Control::Resume).await;
+        }
+    }
+
+    /// Cancel a worker cooperatively and wait for its loop to exit. Unlike
+    /// `JoinHandle::abort`, this lets an in-flight step finish instead of
+    /// cutting it off mid-operation.
+    pub async fn cancel(&self, name: &str) {
+        if let Some(handle) = self.handles.lock().await.remove(name) {
+            let _ = handle.control.send(Control::Cancel).await;
+            let _ = handle.task.await;
+        }
+    }
+
+    /// Snapshot of every registered worker's name, state, last error, and
+    /// iteration count.
+    pub async fn list_workers(&self) -> Vec<WorkerStatus> {
+        self.statuses.lock().await.values().cloned().collect()
+    }
+}
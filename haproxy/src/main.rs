@@ -4,15 +4,57 @@
 //! in environment variables. Supports single-node and multi-node modes with
 //! TCP/HTTP health checks via Patroni.
 
+mod monitoring;
+mod runtime_api;
+
 use anyhow::{anyhow, Context, Result};
+use common::Telemetry;
 use std::env;
 use std::fs;
-use std::os::unix::process::CommandExt;
-use std::process::Command;
 use tracing::info;
 
 const CONFIG_FILE: &str = "/usr/local/etc/haproxy/haproxy.cfg";
 
+/// Which IP address family HAProxy resolves node hostnames as and binds its
+/// frontends on. Railway's internal network (`*.railway.internal`) is
+/// IPv6-only, much like garage's `[fc00::]` peer addressing, so `Ipv6` is
+/// the default; `Ipv4`/`Dual` stay available for local/dev setups that
+/// don't have IPv6 routing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddressFamily {
+    Ipv4,
+    Ipv6,
+    Dual,
+}
+
+impl AddressFamily {
+    fn from_env() -> Self {
+        match env::var("HAPROXY_ADDRESS_FAMILY").unwrap_or_default().to_lowercase().as_str() {
+            "ipv4" => Self::Ipv4,
+            "dual" => Self::Dual,
+            _ => Self::Ipv6,
+        }
+    }
+
+    /// The `resolve-prefer` value passed to `resolvers railway` server
+    /// lines.
+    fn resolve_prefer(&self) -> &'static str {
+        match self {
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 | Self::Dual => "ipv6",
+        }
+    }
+
+    /// The `bind` address for a frontend listening on `port`.
+    fn bind_address(&self, port: &str) -> String {
+        match self {
+            Self::Ipv4 => format!("*:{}", port),
+            Self::Ipv6 => format!("ipv6@:::{}", port),
+            Self::Dual => format!(":::{}", port),
+        }
+    }
+}
+
 struct Config {
     postgres_nodes: String,
     max_conn: String,
@@ -20,6 +62,13 @@ struct Config {
     timeout_client: String,
     timeout_server: String,
     check_interval: String,
+    pgbouncer_enabled: bool,
+    pool_port_session: String,
+    pool_port_transaction: String,
+    runtime_api_enabled: bool,
+    runtime_api_socket: String,
+    runtime_api_spare_slots: u32,
+    address_family: AddressFamily,
 }
 
 impl Config {
@@ -38,59 +87,156 @@ impl Config {
                 .unwrap_or_else(|_| "30m".to_string()),
             check_interval: env::var("HAPROXY_CHECK_INTERVAL")
                 .unwrap_or_else(|_| "3s".to_string()),
+            pgbouncer_enabled: env::var("PGBOUNCER_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            pool_port_session: env::var("POOL_PORT_SESSION").unwrap_or_else(|_| "5434".to_string()),
+            pool_port_transaction: env::var("POOL_PORT_TRANSACTION")
+                .unwrap_or_else(|_| "6432".to_string()),
+            runtime_api_enabled: env::var("HAPROXY_RUNTIME_API_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            runtime_api_socket: env::var("HAPROXY_RUNTIME_API_SOCKET")
+                .unwrap_or_else(|_| "/var/run/haproxy/admin.sock".to_string()),
+            runtime_api_spare_slots: env::var("HAPROXY_RUNTIME_API_SPARE_SLOTS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(2),
+            address_family: AddressFamily::from_env(),
         })
     }
 }
 
-#[derive(Debug)]
-struct PostgresNode {
-    name: String,
-    host: String,
+#[derive(Debug, Clone)]
+pub(crate) struct PostgresNode {
+    pub(crate) name: String,
+    pub(crate) host: String,
     pg_port: String,
-    patroni_port: String,
+    pub(crate) patroni_port: String,
+}
+
+/// Splits one `host:pgport:patroniport` entry into its three fields. A host
+/// that's a bare IPv6 literal carries its own colons, so it must be
+/// bracketed (`[fc00::1]:5432:8008`) the same way HAProxy/URLs require -
+/// otherwise `:` can't tell a host colon from a field separator.
+fn split_node_entry(node: &str) -> Result<(String, &str, &str)> {
+    if let Some(after_bracket) = node.strip_prefix('[') {
+        let close = after_bracket
+            .find(']')
+            .ok_or_else(|| anyhow!("Invalid node format: {}. Unterminated '[' in IPv6 literal", node))?;
+        let host = format!("[{}]", &after_bracket[..close]);
+        let rest = after_bracket[close + 1..]
+            .strip_prefix(':')
+            .ok_or_else(|| anyhow!("Invalid node format: {}. Expected [ipv6]:pgport:patroniport", node))?;
+        let parts: Vec<&str> = rest.split(':').collect();
+        if parts.len() != 2 {
+            return Err(anyhow!("Invalid node format: {}. Expected [ipv6]:pgport:patroniport", node));
+        }
+        Ok((host, parts[0], parts[1]))
+    } else {
+        let parts: Vec<&str> = node.split(':').collect();
+        if parts.len() != 3 {
+            return Err(anyhow!(
+                "Invalid node format: {}. Expected: hostname:pgport:patroniport",
+                node
+            ));
+        }
+        Ok((parts[0].to_string(), parts[1], parts[2]))
+    }
 }
 
 fn parse_nodes(postgres_nodes: &str) -> Result<Vec<PostgresNode>> {
     postgres_nodes
         .split(',')
-        .map(|node| {
-            let parts: Vec<&str> = node.split(':').collect();
-            if parts.len() != 3 {
-                return Err(anyhow!(
-                    "Invalid node format: {}. Expected: hostname:pgport:patroniport",
-                    node
-                ));
-            }
-
-            let host = parts[0].to_string();
-            // Extract short name from hostname (e.g., postgres-1 from postgres-1.railway.internal)
-            let name = host.split('.').next().unwrap_or(&host).to_string();
+        .enumerate()
+        .map(|(i, node)| {
+            let (host, pg_port, patroni_port) = split_node_entry(node)?;
+
+            // Extract short name from hostname (e.g., postgres-1 from
+            // postgres-1.railway.internal); bracketed IPv6 literals have no
+            // meaningful short form, so fall back to a positional name.
+            let name = if host.starts_with('[') {
+                format!("node-{}", i)
+            } else {
+                host.split('.').next().unwrap_or(&host).to_string()
+            };
 
             Ok(PostgresNode {
                 name,
                 host,
-                pg_port: parts[1].to_string(),
-                patroni_port: parts[2].to_string(),
+                pg_port: pg_port.to_string(),
+                patroni_port: patroni_port.to_string(),
             })
         })
         .collect()
 }
 
-fn generate_server_entries(nodes: &[PostgresNode], single_node_mode: bool) -> String {
-    nodes
+// When `runtime_managed` is set, real nodes come up `disabled` and a few
+// `spare-N` placeholder slots are appended - the Runtime API reconcile loop
+// (see `monitoring::runtime_api_reconcile_loop`) fills in real addresses and
+// enables servers at runtime, so joining/leaving/failing over never needs a
+// config rewrite + reload.
+fn generate_server_entries(
+    nodes: &[PostgresNode],
+    single_node_mode: bool,
+    runtime_managed: bool,
+    spare_slots: u32,
+    address_family: AddressFamily,
+) -> String {
+    let disabled_suffix = if runtime_managed { " disabled" } else { "" };
+    let resolve_prefer = address_family.resolve_prefer();
+
+    let mut lines: Vec<String> = nodes
         .iter()
         .map(|node| {
             if single_node_mode {
                 // Single node: skip Patroni health check, use TCP check on PostgreSQL port
                 format!(
-                    "    server {} {}:{} check resolvers railway resolve-prefer ipv4",
-                    node.name, node.host, node.pg_port
+                    "    server {} {}:{} check resolvers railway resolve-prefer {}{}",
+                    node.name, node.host, node.pg_port, resolve_prefer, disabled_suffix
                 )
             } else {
                 // Multi-node: use Patroni health check
                 format!(
-                    "    server {} {}:{} check port {} resolvers railway resolve-prefer ipv4",
-                    node.name, node.host, node.pg_port, node.patroni_port
+                    "    server {} {}:{} check port {} resolvers railway resolve-prefer {}{}",
+                    node.name, node.host, node.pg_port, node.patroni_port, resolve_prefer, disabled_suffix
+                )
+            }
+        })
+        .collect();
+
+    if runtime_managed {
+        for i in 0..spare_slots {
+            lines.push(format!("    server spare-{} 127.0.0.1:1 disabled", i));
+        }
+    }
+
+    lines.join("\n")
+}
+
+// Same shape as `generate_server_entries`, but connects to `pool_port` (a
+// PgBouncer sidecar colocated with each node) instead of the node's direct
+// PostgreSQL port, while still health-checking via Patroni like the direct
+// primary backend does.
+fn generate_pool_server_entries(
+    nodes: &[PostgresNode],
+    pool_port: &str,
+    single_node_mode: bool,
+    address_family: AddressFamily,
+) -> String {
+    let resolve_prefer = address_family.resolve_prefer();
+    nodes
+        .iter()
+        .map(|node| {
+            if single_node_mode {
+                format!(
+                    "    server {} {}:{} check resolvers railway resolve-prefer {}",
+                    node.name, node.host, pool_port, resolve_prefer
+                )
+            } else {
+                format!(
+                    "    server {} {}:{} check port {} resolvers railway resolve-prefer {}",
+                    node.name, node.host, pool_port, node.patroni_port, resolve_prefer
                 )
             }
         })
@@ -98,6 +244,50 @@ fn generate_server_entries(nodes: &[PostgresNode], single_node_mode: bool) -> St
         .join("\n")
 }
 
+// Builds one `frontend`/`backend` pair for a pooled (PgBouncer) writer path.
+// Pooling targets the primary the same way the direct 5432 path does -
+// transaction/session multiplexing is valuable for the high-connection write
+// path; read traffic can keep using the direct 5433 replica path.
+fn generate_pool_frontend(
+    label: &str,
+    bind_address: &str,
+    entries: &str,
+    check_interval: &str,
+    single_node_mode: bool,
+) -> String {
+    let backend_name = format!("postgresql_primary_pool_{}_backend", label);
+    let frontend_name = format!("postgresql_primary_pool_{}", label);
+
+    let backend = if single_node_mode {
+        format!(
+            r#"backend {}
+    default-server inter {} fall 3 rise 2 on-marked-down shutdown-sessions
+{}"#,
+            backend_name, check_interval, entries
+        )
+    } else {
+        format!(
+            r#"backend {}
+    option httpchk
+    http-check send meth GET uri /primary
+    http-check expect status 200
+    default-server inter {} fall 3 rise 2 on-marked-down shutdown-sessions
+{}"#,
+            backend_name, check_interval, entries
+        )
+    };
+
+    format!(
+        r#"# PostgreSQL via PgBouncer ({} pooling, read-write)
+frontend {}
+    bind {}
+    default_backend {}
+
+{}"#,
+        label, frontend_name, bind_address, backend_name, backend
+    )
+}
+
 fn generate_config(config: &Config) -> Result<String> {
     let nodes = parse_nodes(&config.postgres_nodes)?;
     let node_count = nodes.len();
@@ -107,7 +297,13 @@ fn generate_config(config: &Config) -> Result<String> {
         info!("Single node mode: HAProxy will route directly to PostgreSQL without Patroni health checks");
     }
 
-    let server_entries = generate_server_entries(&nodes, single_node_mode);
+    let server_entries = generate_server_entries(
+        &nodes,
+        single_node_mode,
+        config.runtime_api_enabled,
+        config.runtime_api_spare_slots,
+        config.address_family,
+    );
 
     let primary_backend = if single_node_mode {
         format!(
@@ -149,11 +345,54 @@ fn generate_config(config: &Config) -> Result<String> {
         )
     };
 
+    let pooling_frontends = if config.pgbouncer_enabled {
+        let session_entries =
+            generate_pool_server_entries(&nodes, &config.pool_port_session, single_node_mode, config.address_family);
+        let transaction_entries = generate_pool_server_entries(
+            &nodes,
+            &config.pool_port_transaction,
+            single_node_mode,
+            config.address_family,
+        );
+
+        format!(
+            "\n{}\n\n{}\n",
+            generate_pool_frontend(
+                "session",
+                &config.address_family.bind_address(&config.pool_port_session),
+                &session_entries,
+                &config.check_interval,
+                single_node_mode
+            ),
+            generate_pool_frontend(
+                "transaction",
+                &config.address_family.bind_address(&config.pool_port_transaction),
+                &transaction_entries,
+                &config.check_interval,
+                single_node_mode
+            )
+        )
+    } else {
+        String::new()
+    };
+
+    // Required for the Runtime API reconcile loop (see
+    // `monitoring::runtime_api_reconcile_loop`) to issue `add server`/
+    // `set server`/`enable server` commands without a reload.
+    let stats_socket_line = if config.runtime_api_enabled {
+        format!("    stats socket {} mode 660 level admin\n", config.runtime_api_socket)
+    } else {
+        String::new()
+    };
+
+    let primary_bind = config.address_family.bind_address("5432");
+    let replica_bind = config.address_family.bind_address("5433");
+
     Ok(format!(
         r#"global
     maxconn {}
     log stdout format raw local0
-
+{}
 defaults
     log global
     mode tcp
@@ -175,38 +414,45 @@ resolvers railway
     hold valid      10s
     hold obsolete   10s
 
-# Stats page for monitoring
+# Stats page for monitoring, plus HAProxy's built-in Prometheus exporter on
+# the same listener (no separate exporter sidecar needed)
 listen stats
     bind *:8404
     mode http
     stats enable
     stats uri /stats
     stats refresh 10s
+    http-request use-service prometheus-exporter if {{ path /metrics }}
 
 # Primary PostgreSQL (read-write)
 frontend postgresql_primary
-    bind *:5432
+    bind {}
     default_backend postgresql_primary_backend
 
 {}
 
 # Replica PostgreSQL (read-only)
 frontend postgresql_replicas
-    bind *:5433
+    bind {}
     default_backend postgresql_replicas_backend
 
 {}
-"#,
+{}"#,
         config.max_conn,
+        stats_socket_line,
         config.timeout_connect,
         config.timeout_client,
         config.timeout_server,
+        primary_bind,
         primary_backend,
-        replica_backend
+        replica_bind,
+        replica_backend,
+        pooling_frontends
     ))
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -216,10 +462,15 @@ fn main() -> Result<()> {
         .with_target(false)
         .init();
 
+    common::merge_dotenv()?;
+
     let config = Config::from_env()?;
 
     info!("Generating HAProxy config for nodes: {}", config.postgres_nodes);
 
+    let nodes = parse_nodes(&config.postgres_nodes)?;
+    let single_node_mode = nodes.len() == 1;
+
     let haproxy_config = generate_config(&config)?;
 
     // Write config file
@@ -233,10 +484,37 @@ fn main() -> Result<()> {
     }
 
     info!("Starting HAProxy...");
-
-    // exec haproxy (replaces current process)
-    let err = Command::new("haproxy").arg("-f").arg(CONFIG_FILE).exec();
-
-    // exec only returns if there was an error
-    Err(anyhow!("Failed to exec haproxy: {}", err))
+    let telemetry = Telemetry::from_env("haproxy");
+
+    // Spawned (not exec'd) so the monitoring loop below can watch it and
+    // react to its exit instead of replacing this process entirely.
+    let child = tokio::process::Command::new("haproxy")
+        .arg("-f")
+        .arg(CONFIG_FILE)
+        .spawn()
+        .context("Failed to start HAProxy")?;
+
+    tokio::spawn(
+        common::ProxyMetricsScraper::new(
+            "http://localhost:8404/metrics",
+            std::time::Duration::from_secs(5),
+            vec!["postgresql_primary_backend".to_string()],
+        )
+        .run(telemetry.clone()),
+    );
+
+    common::spawn_audits(
+        common::AuditConfig::from_env(),
+        telemetry.clone(),
+        nodes
+            .iter()
+            .map(|n| common::AuditNode { host: n.host.clone(), patroni_port: n.patroni_port.clone() })
+            .collect(),
+        env::var("ETCD_ENDPOINTS")
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default(),
+        monitoring::STATS_URL.to_string(),
+    );
+
+    monitoring::run_monitoring_loop(child, &telemetry, single_node_mode, nodes).await
 }
@@ -1,104 +1,633 @@
 //! HAProxy process monitoring
 //!
-//! Monitors HAProxy backend health and emits telemetry when no primary is available.
+//! Monitors HAProxy backend health and emits telemetry when no primary is
+//! available. Also runs an embedded Prometheus metrics server so operators
+//! can scrape continuous cluster state instead of only seeing it as discrete
+//! telemetry events.
+//!
+//! Runs as a task on the caller's Tokio runtime (rather than a dedicated OS
+//! thread with its own blocking HTTP client) so it shares the process's
+//! async HTTP/etcd clients and can be cancelled cleanly on shutdown instead
+//! of reaching for `std::process::exit` on every exit path.
 
-use anyhow::Result;
-use common::{Telemetry, TelemetryEvent};
-use std::process::Child;
-use std::thread;
-use std::time::Duration;
+use crate::runtime_api::RuntimeApiClient;
+use crate::PostgresNode;
+use anyhow::{Context, Result};
+use common::{EtcdClient, Telemetry, TelemetryEvent};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::process::Child;
+use tokio::signal::unix::{signal, SignalKind};
 use tracing::{error, info, warn};
 
-const STATS_URL: &str = "http://localhost:8404/stats;csv";
+pub(crate) const STATS_URL: &str = "http://localhost:8404/stats;csv";
 const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const ETCD_SCRAPE_INTERVAL: Duration = Duration::from_secs(5);
+const PATRONI_SCRAPE_INTERVAL: Duration = Duration::from_secs(5);
+const PRIMARY_BACKEND: &str = "postgresql_primary_backend";
+const REPLICA_BACKEND: &str = "postgresql_replicas_backend";
+const RUNTIME_API_RECONCILE_INTERVAL: Duration = Duration::from_secs(5);
+/// Consecutive failed `/health` polls before a node is held in `maint`
+/// rather than left `ready` and relying on HAProxy's own `check` to notice.
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Config for the embedded metrics server, read from the environment.
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// etcd client endpoints (e.g. `http://etcd-1:2379`). Empty disables the
+    /// `etcd_*` gauges without disabling the HAProxy-side ones.
+    pub etcd_endpoints: Vec<String>,
+}
+
+impl MetricsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("HAPROXY_METRICS_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            port: std::env::var("HAPROXY_METRICS_PORT")
+                .unwrap_or_else(|_| "9101".to_string())
+                .parse()
+                .unwrap_or(9101),
+            etcd_endpoints: std::env::var("ETCD_ENDPOINTS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Config for the Runtime API reconcile loop, read from the environment.
+/// Mirrors `haproxy::Config`'s `runtime_api_*` fields, which can't be
+/// imported directly since they're private to `main.rs`.
+pub struct RuntimeApiConfig {
+    pub enabled: bool,
+    pub socket_path: String,
+}
+
+impl RuntimeApiConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("HAPROXY_RUNTIME_API_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            socket_path: std::env::var("HAPROXY_RUNTIME_API_SOCKET")
+                .unwrap_or_else(|_| "/var/run/haproxy/admin.sock".to_string()),
+        }
+    }
+}
+
+/// Per-node health bookkeeping for the reconcile loop, analogous to the
+/// reconnection records lua-cassandra's cluster module keeps per host: a
+/// node isn't dropped the instant a poll fails, only after
+/// `MAX_CONSECUTIVE_FAILURES` in a row, and it's tracked continuously so a
+/// flapping node doesn't thrash the backend every tick.
+struct NodeHealthRecord {
+    up: bool,
+    last_seen: Instant,
+    consecutive_failures: u32,
+}
+
+/// Counts and per-server status parsed from one HAProxy stats CSV scrape.
+struct BackendStats {
+    healthy_count: usize,
+    servers: Vec<(String, bool)>,
+}
+
+/// Gauges shared between the backend-health loop, the etcd scrape loop, and
+/// the HTTP server that renders them on scrape.
+#[derive(Default)]
+struct Snapshot {
+    haproxy_up: f64,
+    backend_healthy_servers: f64,
+    servers: Vec<(String, bool)>,
+    etcd_cluster_members: f64,
+    etcd_learner_members: f64,
+    etcd_cluster_healthy: f64,
+    patroni_nodes: Vec<PatroniNodeMetrics>,
+}
+
+/// One node's Patroni-reported state, as last scraped from its REST API.
+#[derive(Clone)]
+struct PatroniNodeMetrics {
+    server: String,
+    role: String,
+    running: f64,
+    pending_restart: f64,
+    postgres_version: f64,
+    patroni_version: String,
+    timeline_number: f64,
+    replication_slots: f64,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PatroniInfo {
+    version: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PatroniApiResponse {
+    state: Option<String>,
+    role: Option<String>,
+    server_version: Option<u64>,
+    timeline: Option<u64>,
+    pending_restart: Option<bool>,
+    #[serde(default)]
+    patroni: PatroniInfo,
+    #[serde(default)]
+    replication: Vec<serde_json::Value>,
+}
+
+fn render(snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+
+    out.push_str("# HELP haproxy_up Whether the HAProxy process is running\n");
+    out.push_str("# TYPE haproxy_up gauge\n");
+    out.push_str(&format!("haproxy_up {}\n", snapshot.haproxy_up));
+
+    out.push_str("# HELP haproxy_backend_healthy_servers Number of UP servers in a backend\n");
+    out.push_str("# TYPE haproxy_backend_healthy_servers gauge\n");
+    out.push_str(&format!(
+        "haproxy_backend_healthy_servers{{backend=\"{}\"}} {}\n",
+        PRIMARY_BACKEND, snapshot.backend_healthy_servers
+    ));
+
+    out.push_str("# HELP haproxy_backend_server_up Whether an individual backend server is UP\n");
+    out.push_str("# TYPE haproxy_backend_server_up gauge\n");
+    for (name, up) in &snapshot.servers {
+        out.push_str(&format!(
+            "haproxy_backend_server_up{{backend=\"{}\",server=\"{}\"}} {}\n",
+            PRIMARY_BACKEND,
+            name,
+            if *up { 1 } else { 0 }
+        ));
+    }
+
+    out.push_str("# HELP etcd_cluster_members Number of members in the etcd cluster\n");
+    out.push_str("# TYPE etcd_cluster_members gauge\n");
+    out.push_str(&format!("etcd_cluster_members {}\n", snapshot.etcd_cluster_members));
+
+    out.push_str("# HELP etcd_learner_members Number of non-voting learner members in the etcd cluster\n");
+    out.push_str("# TYPE etcd_learner_members gauge\n");
+    out.push_str(&format!("etcd_learner_members {}\n", snapshot.etcd_learner_members));
+
+    out.push_str("# HELP etcd_cluster_healthy Whether a voting etcd member answered the last membership scrape\n");
+    out.push_str("# TYPE etcd_cluster_healthy gauge\n");
+    out.push_str(&format!("etcd_cluster_healthy {}\n", snapshot.etcd_cluster_healthy));
+
+    out.push_str("# HELP patroni_running Whether Patroni reports its local state as \"running\"\n");
+    out.push_str("# TYPE patroni_running gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_running{{server=\"{}\",role=\"{}\"}} {}\n",
+            n.server, n.role, n.running
+        ));
+    }
+
+    out.push_str("# HELP patroni_pending_restart Whether a node is pending a restart to apply changed parameters\n");
+    out.push_str("# TYPE patroni_pending_restart gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_pending_restart{{server=\"{}\",role=\"{}\"}} {}\n",
+            n.server, n.role, n.pending_restart
+        ));
+    }
+
+    out.push_str("# HELP patroni_postgres_version Running PostgreSQL server_version as reported by Patroni\n");
+    out.push_str("# TYPE patroni_postgres_version gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_postgres_version{{server=\"{}\",role=\"{}\"}} {}\n",
+            n.server, n.role, n.postgres_version
+        ));
+    }
+
+    out.push_str("# HELP patroni_version Patroni's own version, as a label on a constant gauge\n");
+    out.push_str("# TYPE patroni_version gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_version{{server=\"{}\",role=\"{}\",version=\"{}\"}} 1\n",
+            n.server, n.role, n.patroni_version
+        ));
+    }
+
+    out.push_str("# HELP patroni_timeline_number Current PostgreSQL timeline\n");
+    out.push_str("# TYPE patroni_timeline_number gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_timeline_number{{server=\"{}\",role=\"{}\"}} {}\n",
+            n.server, n.role, n.timeline_number
+        ));
+    }
+
+    out.push_str("# HELP patroni_replication_slots Number of active replication connections reported by Patroni\n");
+    out.push_str("# TYPE patroni_replication_slots gauge\n");
+    for n in &snapshot.patroni_nodes {
+        out.push_str(&format!(
+            "patroni_replication_slots{{server=\"{}\",role=\"{}\"}} {}\n",
+            n.server, n.role, n.replication_slots
+        ));
+    }
+
+    out
+}
+
+/// Minimal HTTP/1.1 listener: any request gets the current metrics snapshot
+/// back as `text/plain`. Mirrors `postgres-patroni`'s metrics exporter.
+async fn serve_loop(port: u16, snapshot: Arc<Mutex<Snapshot>>) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(l) => l,
+        Err(e) => {
+            warn!(port, error = %e, "failed to bind metrics listener");
+            return;
+        }
+    };
+
+    info!(port, "metrics exporter listening");
+
+    loop {
+        let (mut stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "failed to accept metrics connection");
+                continue;
+            }
+        };
+
+        let snapshot = Arc::clone(&snapshot);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            // Drain (and discard) the request; we don't need to route on
+            // path/method since this listener only ever serves metrics.
+            let _ = stream.read(&mut buf).await;
+
+            let body = render(&snapshot.lock().unwrap());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Periodically scrape etcd membership for the `etcd_*` gauges. A no-op if
+/// no endpoints are configured.
+async fn etcd_scrape_loop(endpoints: Vec<String>, snapshot: Arc<Mutex<Snapshot>>) {
+    if endpoints.is_empty() {
+        return;
+    }
+
+    let client = EtcdClient::new(endpoints.clone());
+    let mut ticker = tokio::time::interval(ETCD_SCRAPE_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        let Some(endpoint) = client.first_healthy(&endpoints).await else {
+            snapshot.lock().unwrap().etcd_cluster_healthy = 0.0;
+            continue;
+        };
+
+        match client.member_list_via(&endpoint).await {
+            Ok(members) => {
+                let mut snap = snapshot.lock().unwrap();
+                snap.etcd_cluster_members = members.len() as f64;
+                snap.etcd_learner_members = members.iter().filter(|m| m.is_learner).count() as f64;
+                snap.etcd_cluster_healthy = 1.0;
+            }
+            Err(e) => {
+                warn!(error = %e, "failed to list etcd members for metrics");
+                snapshot.lock().unwrap().etcd_cluster_healthy = 0.0;
+            }
+        }
+    }
+}
+
+/// Periodically scrape every node's Patroni REST API for the `patroni_*`
+/// gauges, turning the existing fire-and-forget failover telemetry into
+/// continuous time-series observability. A no-op if no nodes are given.
+async fn patroni_scrape_loop(nodes: Vec<PostgresNode>, snapshot: Arc<Mutex<Snapshot>>) {
+    if nodes.is_empty() {
+        return;
+    }
+
+    let client = match reqwest::Client::builder().timeout(Duration::from_secs(2)).build() {
+        Ok(c) => c,
+        Err(e) => {
+            warn!(error = %e, "failed to build Patroni scrape client");
+            return;
+        }
+    };
+
+    let mut ticker = tokio::time::interval(PATRONI_SCRAPE_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        let mut node_metrics = Vec::with_capacity(nodes.len());
+        for node in &nodes {
+            let url = format!("http://{}:{}/patroni", node.host, node.patroni_port);
+            let response = match client.get(&url).send().await {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            let status: PatroniApiResponse = match response.json().await {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+
+            let role = status.role.unwrap_or_default();
+            node_metrics.push(PatroniNodeMetrics {
+                server: node.name.clone(),
+                running: if status.state.as_deref() == Some("running") { 1.0 } else { 0.0 },
+                pending_restart: if status.pending_restart.unwrap_or(false) { 1.0 } else { 0.0 },
+                postgres_version: status.server_version.unwrap_or(0) as f64,
+                patroni_version: status.patroni.version.unwrap_or_default(),
+                timeline_number: status.timeline.unwrap_or(0) as f64,
+                replication_slots: status.replication.len() as f64,
+                role,
+            });
+        }
+
+        snapshot.lock().unwrap().patroni_nodes = node_metrics;
+    }
+}
+
+/// Poll `node`'s Patroni `/health` endpoint, succeeding only on a 2xx
+/// response - the body isn't inspected, since any reachable, non-erroring
+/// response is enough to consider the node up for routing purposes.
+async fn poll_node_health(client: &reqwest::Client, node: &PostgresNode) -> bool {
+    let url = format!("http://{}:{}/health", node.host, node.patroni_port);
+    matches!(client.get(&url).send().await, Ok(resp) if resp.status().is_success())
+}
+
+/// Diff the desired server set (`nodes`) against what each backend's live
+/// Runtime API set actually has, issuing `add server`/`set server addr`
+/// to fill in spare slots and `set server state maint|ready` as each node's
+/// health flips - all without a config rewrite + reload. Runs forever on
+/// `RUNTIME_API_RECONCILE_INTERVAL`; a no-op if `single_node_mode` (the
+/// cold-start config has no placeholder slots to fill) or the Runtime API
+/// isn't enabled.
+pub async fn runtime_api_reconcile_loop(nodes: Vec<PostgresNode>, config: RuntimeApiConfig, single_node_mode: bool) {
+    if !config.enabled || single_node_mode || nodes.is_empty() {
+        return;
+    }
+
+    let runtime_api = RuntimeApiClient::new(config.socket_path);
+    let health_client = match reqwest::Client::builder().timeout(Duration::from_secs(2)).build() {
+        Ok(c) => c,
+        Err(e) => {
+            warn!(error = %e, "failed to build Runtime API health-poll client");
+            return;
+        }
+    };
+
+    let mut records: HashMap<String, NodeHealthRecord> = nodes
+        .iter()
+        .map(|n| {
+            (
+                n.name.clone(),
+                NodeHealthRecord {
+                    up: false,
+                    last_seen: Instant::now(),
+                    consecutive_failures: 0,
+                },
+            )
+        })
+        .collect();
+
+    let mut ticker = tokio::time::interval(RUNTIME_API_RECONCILE_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        for backend in [PRIMARY_BACKEND, REPLICA_BACKEND] {
+            let live = match runtime_api.live_server_names(backend).await {
+                Ok(names) => names,
+                Err(e) => {
+                    warn!(backend, error = %e, "failed to read live Runtime API server state");
+                    continue;
+                }
+            };
+
+            for node in &nodes {
+                if !live.contains(&node.name) {
+                    if let Err(e) = runtime_api.add_server(backend, &node.name, &node.host, &node.patroni_port).await {
+                        warn!(backend, node = %node.name, error = %e, "failed to add server via Runtime API");
+                        continue;
+                    }
+                }
+            }
+        }
+
+        for node in &nodes {
+            let is_up = poll_node_health(&health_client, node).await;
+            let record = records.entry(node.name.clone()).or_insert_with(|| NodeHealthRecord {
+                up: false,
+                last_seen: Instant::now(),
+                consecutive_failures: 0,
+            });
+
+            if is_up {
+                let was_down = !record.up;
+                record.up = true;
+                record.last_seen = Instant::now();
+                record.consecutive_failures = 0;
+
+                if was_down {
+                    for backend in [PRIMARY_BACKEND, REPLICA_BACKEND] {
+                        if let Err(e) = runtime_api.enable_server(backend, &node.name).await {
+                            warn!(backend, node = %node.name, error = %e, "failed to enable server via Runtime API");
+                        }
+                    }
+                    info!(node = %node.name, "node recovered, enabled in Runtime API");
+                }
+            } else {
+                record.consecutive_failures += 1;
+
+                if record.up && record.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                    record.up = false;
+                    for backend in [PRIMARY_BACKEND, REPLICA_BACKEND] {
+                        if let Err(e) = runtime_api.hold_in_maint(backend, &node.name).await {
+                            warn!(backend, node = %node.name, error = %e, "failed to hold server in maint via Runtime API");
+                        }
+                    }
+                    warn!(node = %node.name, "node unreachable, held in maint");
+                }
+            }
+        }
+    }
+}
+
+/// Spawn the metrics HTTP server and (if etcd endpoints are configured) the
+/// etcd scrape loop as tasks on the caller's runtime. Returns the shared
+/// snapshot so the monitor loop can keep the HAProxy-side gauges current;
+/// `None` if metrics are disabled entirely.
+fn spawn_metrics(config: MetricsConfig, nodes: Vec<PostgresNode>) -> Option<Arc<Mutex<Snapshot>>> {
+    if !config.enabled {
+        return None;
+    }
+
+    let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+
+    let http_snapshot = Arc::clone(&snapshot);
+    tokio::spawn(serve_loop(config.port, http_snapshot));
+
+    let etcd_snapshot = Arc::clone(&snapshot);
+    tokio::spawn(etcd_scrape_loop(config.etcd_endpoints, etcd_snapshot));
+
+    let patroni_snapshot = Arc::clone(&snapshot);
+    tokio::spawn(patroni_scrape_loop(nodes, patroni_snapshot));
+
+    Some(snapshot)
+}
+
+/// Wait for SIGTERM or SIGINT, whichever comes first.
+async fn wait_for_signal() -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate()).context("Failed to install SIGTERM handler")?;
+    let mut sigint = signal(SignalKind::interrupt()).context("Failed to install SIGINT handler")?;
+
+    tokio::select! {
+        _ = sigterm.recv() => info!("Received SIGTERM"),
+        _ = sigint.recv() => info!("Received SIGINT"),
+    }
+
+    Ok(())
+}
 
 /// Run the monitoring loop for HAProxy
 ///
 /// Monitors:
 /// - HAProxy process health
 /// - Backend availability (emits telemetry when no primary available)
-pub fn run_monitoring_loop(
+/// - Both of the above, continuously, via an embedded Prometheus `/metrics`
+///   server (see `MetricsConfig`)
+///
+/// Returns once HAProxy exits, or once a shutdown signal is received (in
+/// which case HAProxy is killed first).
+pub async fn run_monitoring_loop(
     mut child: Child,
     telemetry: &Telemetry,
     single_node_mode: bool,
+    nodes: Vec<PostgresNode>,
 ) -> Result<()> {
     let pid = child.id();
     info!(pid, "HAProxy started, beginning monitoring");
 
+    let runtime_api_config = RuntimeApiConfig::from_env();
+    if runtime_api_config.enabled {
+        tokio::spawn(runtime_api_reconcile_loop(nodes.clone(), runtime_api_config, single_node_mode));
+    }
+
+    let snapshot = spawn_metrics(MetricsConfig::from_env(), nodes);
+    if let Some(snapshot) = &snapshot {
+        snapshot.lock().unwrap().haproxy_up = 1.0;
+    }
+
     // Skip backend monitoring in single node mode - no Patroni health checks
     if single_node_mode {
         info!("Single node mode: skipping backend health monitoring");
-        let status = child.wait()?;
+        let status = child.wait().await?;
+        if let Some(snapshot) = &snapshot {
+            snapshot.lock().unwrap().haproxy_up = 0.0;
+        }
         error!(?status, "HAProxy exited");
         std::process::exit(status.code().unwrap_or(1));
     }
 
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(2))
-        .build()?;
-
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(2)).build()?;
     let mut no_primary_alerted = false;
+    let mut ticker = tokio::time::interval(CHECK_INTERVAL);
 
     loop {
-        // Check if HAProxy is still running
-        match child.try_wait() {
-            Ok(Some(status)) => {
+        tokio::select! {
+            status = child.wait() => {
+                let status = status?;
+                if let Some(snapshot) = &snapshot {
+                    snapshot.lock().unwrap().haproxy_up = 0.0;
+                }
                 error!(?status, "HAProxy exited unexpectedly");
                 std::process::exit(status.code().unwrap_or(1));
             }
-            Ok(None) => {} // Still running
-            Err(e) => {
-                error!(error = %e, "Failed to check HAProxy status");
-                std::process::exit(1);
+            _ = wait_for_signal() => {
+                info!("Shutdown requested, stopping HAProxy...");
+                if let Some(snapshot) = &snapshot {
+                    snapshot.lock().unwrap().haproxy_up = 0.0;
+                }
+                let _ = child.start_kill();
+                let _ = child.wait().await;
+                return Ok(());
             }
-        }
+            _ = ticker.tick() => {
+                match scrape_backend_stats(&client).await {
+                    Ok(stats) => {
+                        if let Some(snapshot) = &snapshot {
+                            let mut snap = snapshot.lock().unwrap();
+                            snap.backend_healthy_servers = stats.healthy_count as f64;
+                            snap.servers = stats.servers.clone();
+                        }
 
-        // Check backend health
-        match check_primary_backend(&client) {
-            Ok(healthy_count) => {
-                if healthy_count == 0 {
-                    if !no_primary_alerted {
-                        warn!("No healthy primary backend - cluster has no leader");
-                        telemetry.send(TelemetryEvent::DcsUnavailable {
-                            node: "haproxy".to_string(),
-                            scope: "postgresql_primary_backend".to_string(),
-                        });
-                        no_primary_alerted = true;
+                        if stats.healthy_count == 0 {
+                            if !no_primary_alerted {
+                                warn!("No healthy primary backend - cluster has no leader");
+                                telemetry.send(TelemetryEvent::DcsUnavailable {
+                                    node: "haproxy".to_string(),
+                                    scope: PRIMARY_BACKEND.to_string(),
+                                });
+                                no_primary_alerted = true;
+                            }
+                        } else {
+                            if no_primary_alerted {
+                                info!(healthy_count = stats.healthy_count, "Primary backend recovered");
+                            }
+                            no_primary_alerted = false;
+                        }
                     }
-                } else {
-                    if no_primary_alerted {
-                        info!(healthy_count, "Primary backend recovered");
+                    Err(e) => {
+                        warn!(error = %e, "Failed to check backend health");
                     }
-                    no_primary_alerted = false;
                 }
             }
-            Err(e) => {
-                warn!(error = %e, "Failed to check backend health");
-            }
         }
-
-        thread::sleep(CHECK_INTERVAL);
     }
 }
 
-/// Check how many healthy servers are in the primary backend
-fn check_primary_backend(client: &reqwest::blocking::Client) -> Result<usize> {
-    let resp = client.get(STATS_URL).send()?;
-    let body = resp.text()?;
+/// Scrape the HAProxy stats CSV for the primary backend's healthy count and
+/// per-server status.
+async fn scrape_backend_stats(client: &reqwest::Client) -> Result<BackendStats> {
+    let resp = client.get(STATS_URL).send().await?;
+    let body = resp.text().await?;
 
     // HAProxy CSV format: pxname,svname,status,...
-    // We want rows where pxname=postgresql_primary_backend and status=UP
-    let healthy_count = body
-        .lines()
-        .filter(|line| {
-            let parts: Vec<&str> = line.split(',').collect();
-            // pxname is column 0, svname is column 1, status is column 17
-            parts.len() > 17
-                && parts[0] == "postgresql_primary_backend"
-                && parts[1] != "BACKEND" // Skip the backend summary row
-                && parts[17] == "UP"
-        })
-        .count();
+    // We want rows where pxname=postgresql_primary_backend, skipping the
+    // synthetic "BACKEND" summary row. pxname is column 0, svname is column
+    // 1, status is column 17.
+    let mut healthy_count = 0;
+    let mut servers = Vec::new();
+
+    for line in body.lines() {
+        let parts: Vec<&str> = line.split(',').collect();
+        if parts.len() <= 17 || parts[0] != PRIMARY_BACKEND || parts[1] == "BACKEND" {
+            continue;
+        }
+
+        let up = parts[17] == "UP";
+        if up {
+            healthy_count += 1;
+        }
+        servers.push((parts[1].to_string(), up));
+    }
 
-    Ok(healthy_count)
+    Ok(BackendStats { healthy_count, servers })
 }
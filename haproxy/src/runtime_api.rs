@@ -0,0 +1,124 @@
+//! HAProxy Runtime API client
+//!
+//! Talks to the Unix socket configured via `stats socket` in `global` to
+//! add, remove, enable, disable, and drain `server` lines without a config
+//! reload. Paired with `monitoring::runtime_api_reconcile_loop`, which
+//! mirrors the per-host health tracking lua-cassandra's cluster module
+//! keeps for reconnecting nodes: a server is held in a "maint" state
+//! (rather than removed outright) while down, so a flapping replica
+//! doesn't churn the backend every time it blips.
+
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+/// The administrative state a server can be set to via `set server ... state <state>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerState {
+    /// Back in rotation.
+    Ready,
+    /// Held out of rotation without being removed - used for a node that's
+    /// currently unhealthy but may reconnect.
+    Maint,
+}
+
+impl ServerState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ready => "ready",
+            Self::Maint => "maint",
+        }
+    }
+}
+
+/// Thin client over HAProxy's Runtime API. Each command opens a fresh
+/// connection and reads until the socket closes - HAProxy closes the
+/// connection after replying unless the session is put into interactive
+/// mode, which this client doesn't use.
+pub struct RuntimeApiClient {
+    socket_path: String,
+}
+
+impl RuntimeApiClient {
+    pub fn new(socket_path: impl Into<String>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    async fn send(&self, command: &str) -> Result<String> {
+        let mut stream = tokio::time::timeout(Duration::from_secs(2), UnixStream::connect(&self.socket_path))
+            .await
+            .context("timed out connecting to HAProxy Runtime API socket")?
+            .with_context(|| format!("failed to connect to HAProxy Runtime API socket at {}", self.socket_path))?;
+
+        stream
+            .write_all(format!("{}\n", command).as_bytes())
+            .await
+            .context("failed to write Runtime API command")?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .context("failed to read Runtime API response")?;
+
+        Ok(response)
+    }
+
+    /// `add server <backend>/<name> <addr>:<port>` - registers a brand new
+    /// server line that wasn't in the cold-start config at all.
+    pub async fn add_server(&self, backend: &str, name: &str, addr: &str, port: &str) -> Result<()> {
+        let resp = self
+            .send(&format!("add server {}/{} {}:{}", backend, name, addr, port))
+            .await?;
+        if resp.to_lowercase().contains("error") {
+            bail!("add server {}/{} failed: {}", backend, name, resp.trim());
+        }
+        Ok(())
+    }
+
+    /// `set server <backend>/<name> addr <addr> port <port>` - repoints an
+    /// existing (likely spare) slot at a real node.
+    pub async fn set_server_addr(&self, backend: &str, name: &str, addr: &str, port: &str) -> Result<()> {
+        let resp = self
+            .send(&format!("set server {}/{} addr {} port {}", backend, name, addr, port))
+            .await?;
+        if resp.to_lowercase().contains("error") {
+            bail!("set server addr {}/{} failed: {}", backend, name, resp.trim());
+        }
+        Ok(())
+    }
+
+    pub async fn set_state(&self, backend: &str, name: &str, state: ServerState) -> Result<()> {
+        let resp = self
+            .send(&format!("set server {}/{} state {}", backend, name, state.as_str()))
+            .await?;
+        if resp.to_lowercase().contains("error") {
+            bail!("set server {}/{} state failed: {}", backend, name, resp.trim());
+        }
+        Ok(())
+    }
+
+    pub async fn enable_server(&self, backend: &str, name: &str) -> Result<()> {
+        self.set_state(backend, name, ServerState::Ready).await
+    }
+
+    /// Hold a server out of rotation without removing it, so a flapping
+    /// node can come back without re-registering.
+    pub async fn hold_in_maint(&self, backend: &str, name: &str) -> Result<()> {
+        self.set_state(backend, name, ServerState::Maint).await
+    }
+
+    /// The server names `show servers state <backend>` currently reports
+    /// for that backend (column 4 of the non-comment lines).
+    pub async fn live_server_names(&self, backend: &str) -> Result<Vec<String>> {
+        let resp = self.send(&format!("show servers state {}", backend)).await?;
+        Ok(resp
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+            .filter_map(|line| line.split_whitespace().nth(3).map(str::to_string))
+            .collect())
+    }
+}
@@ -1,10 +1,218 @@
-//! Patroni callback for role changes (failover detection)
+//! Patroni callback for on_role_change/on_start/on_stop (failover detection
+//! and leader routing)
 //!
 //! Called by Patroni with: $1=action $2=role $3=scope
-//! Sends telemetry to Railway backboard for monitoring/alerting
+//! Fans failover/rejoin telemetry out to one or more configured alert sinks
+//! (Railway GraphQL, a generic JSON webhook, a Slack-style incoming
+//! webhook), maintains a well-known "this node is primary" marker file for
+//! external routers to poll, and optionally runs an operator-supplied
+//! command (e.g. to update a Railway/Consul service tag) so load balancers
+//! pick up the new leader.
 
 use chrono::Utc;
 use std::env;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Marker file written while this node is primary, removed otherwise. A
+/// simple, dependency-free signal external health checks/routers can poll
+/// without talking to the Patroni REST API.
+const PRIMARY_MARKER_PATH: &str = "/tmp/.patroni_is_primary";
+
+/// Overall time budget for alerting, so a slow/unreachable sink (even after
+/// retries) never blocks Patroni's callback pipeline.
+const ALERT_BUDGET: Duration = Duration::from_secs(5);
+const ALERT_ATTEMPTS: u32 = 3;
+
+/// A normalized failover/rejoin event, serialized differently per sink.
+#[derive(Clone)]
+struct RoleChangeEvent {
+    event_type: &'static str,
+    message: &'static str,
+    node: String,
+    scope: String,
+    role: String,
+    service_id: String,
+    project_id: String,
+    environment_id: String,
+    timestamp: String,
+}
+
+/// Where to send a `RoleChangeEvent`. Each variant owns the config it needs
+/// to build its own request.
+enum AlertSink {
+    RailwayGraphql { endpoint: String },
+    Webhook { url: String },
+    Slack { url: String },
+}
+
+/// Read `ALERT_SINKS` (comma-separated: `railway`, `webhook`, `slack`) and
+/// resolve each named sink's config from its own env var, skipping (with a
+/// local log line) any sink that's named but missing its required config.
+/// Defaults to `railway` alone when unset, preserving the previous
+/// single-sink behavior.
+fn configured_sinks() -> Vec<AlertSink> {
+    let names = env::var("ALERT_SINKS").unwrap_or_else(|_| "railway".to_string());
+
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter_map(|name| match name.to_lowercase().as_str() {
+            "railway" => Some(AlertSink::RailwayGraphql {
+                endpoint: env::var("RAILWAY_GRAPHQL_ENDPOINT").unwrap_or_else(|_| {
+                    "https://backboard.railway.app/graphql/internal".to_string()
+                }),
+            }),
+            "webhook" => match env::var("ALERT_WEBHOOK_URL") {
+                Ok(url) => Some(AlertSink::Webhook { url }),
+                Err(_) => {
+                    eprintln!("ALERT_SINKS includes 'webhook' but ALERT_WEBHOOK_URL is not set, skipping");
+                    None
+                }
+            },
+            "slack" => match env::var("ALERT_SLACK_WEBHOOK_URL") {
+                Ok(url) => Some(AlertSink::Slack { url }),
+                Err(_) => {
+                    eprintln!("ALERT_SINKS includes 'slack' but ALERT_SLACK_WEBHOOK_URL is not set, skipping");
+                    None
+                }
+            },
+            other => {
+                eprintln!("Unrecognized ALERT_SINKS entry {:?}, skipping", other);
+                None
+            }
+        })
+        .collect()
+}
+
+impl AlertSink {
+    fn url(&self) -> &str {
+        match self {
+            Self::RailwayGraphql { endpoint } => endpoint,
+            Self::Webhook { url } => url,
+            Self::Slack { url } => url,
+        }
+    }
+
+    fn payload(&self, event: &RoleChangeEvent) -> serde_json::Value {
+        let metadata = format!(
+            "node={}, role={}, scope={}, serviceId={}, projectId={}, environmentId={}",
+            event.node, event.role, event.scope, event.service_id, event.project_id, event.environment_id
+        );
+
+        match self {
+            Self::RailwayGraphql { .. } => serde_json::json!({
+                "query": "mutation telemetrySend($input: TelemetrySendInput!) { telemetrySend(input: $input) }",
+                "variables": {
+                    "input": {
+                        "command": event.event_type,
+                        "error": event.message,
+                        "stacktrace": metadata,
+                        "projectId": event.project_id,
+                        "environmentId": event.environment_id,
+                        "version": "postgres-ha"
+                    }
+                }
+            }),
+            Self::Webhook { .. } => serde_json::json!({
+                "event_type": event.event_type,
+                "message": event.message,
+                "node": event.node,
+                "scope": event.scope,
+                "role": event.role,
+                "service_id": event.service_id,
+                "project_id": event.project_id,
+                "environment_id": event.environment_id,
+                "timestamp": event.timestamp,
+            }),
+            Self::Slack { .. } => serde_json::json!({
+                "text": format!(
+                    "[{}] {}: {} (node={}, scope={}, service={})",
+                    event.timestamp, event.event_type, event.message, event.node, event.scope, event.service_id
+                )
+            }),
+        }
+    }
+
+    /// Send `event` to this sink, retrying up to `ALERT_ATTEMPTS` times with
+    /// exponential backoff, all within `remaining_budget`. Stops early
+    /// (without another attempt) once there isn't enough budget left for a
+    /// request to plausibly complete.
+    fn send(&self, event: &RoleChangeEvent, remaining_budget: Duration) {
+        let payload = self.payload(event);
+        let deadline = std::time::Instant::now() + remaining_budget;
+
+        let Ok(client) = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_millis(1500))
+            .build()
+        else {
+            return;
+        };
+
+        let mut backoff = Duration::from_millis(100);
+        for attempt in 1..=ALERT_ATTEMPTS {
+            if std::time::Instant::now() >= deadline {
+                break;
+            }
+
+            let result = client
+                .post(self.url())
+                .header("Content-Type", "application/json")
+                .json(&payload)
+                .send();
+
+            match result {
+                Ok(resp) if resp.status().is_success() => return,
+                _ if attempt == ALERT_ATTEMPTS => return,
+                _ => {
+                    std::thread::sleep(backoff.min(deadline.saturating_duration_since(std::time::Instant::now())));
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+}
+
+/// Keep `PRIMARY_MARKER_PATH` in sync with the reported role: present while
+/// this node is primary, absent otherwise (including on `on_stop`).
+fn update_primary_marker(action: &str, role: &str) {
+    let is_primary = action != "on_stop" && matches!(role, "master" | "primary" | "standby_leader");
+
+    if is_primary {
+        let _ = std::fs::write(PRIMARY_MARKER_PATH, "");
+    } else {
+        let _ = std::fs::remove_file(PRIMARY_MARKER_PATH);
+    }
+}
+
+/// Run the operator-supplied `PATRONI_CALLBACK_COMMAND` (e.g. a script that
+/// updates a Railway/Consul service tag), passing `action role scope` as
+/// argv so it can react to leader changes the same way this binary does.
+/// Gated by `PATRONI_CALLBACK_ENABLED` and best-effort: a failing command
+/// must never block Patroni's callback pipeline.
+fn run_callback_command(action: &str, role: &str, scope: &str) {
+    let enabled = env::var("PATRONI_CALLBACK_ENABLED")
+        .map(|v| v.to_lowercase() == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+
+    let Ok(command) = env::var("PATRONI_CALLBACK_COMMAND") else {
+        return;
+    };
+
+    let _ = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .arg("--")
+        .arg(action)
+        .arg(role)
+        .arg(scope)
+        .stdin(Stdio::null())
+        .status();
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -13,73 +221,67 @@ fn main() {
     let role = args.get(2).map(|s| s.as_str()).unwrap_or("");
     let scope = args.get(3).map(|s| s.as_str()).unwrap_or("");
 
-    // Only proceed for role changes
+    // Only proceed for the callback hooks we're wired up for in patroni.yml.
+    if !matches!(action, "on_role_change" | "on_start" | "on_stop") {
+        std::process::exit(0);
+    }
+
+    update_primary_marker(action, role);
+    run_callback_command(action, role, scope);
+
+    // Only role changes are worth telemetry; on_start/on_stop fire on every
+    // restart and would otherwise drown out real failover events.
     if action != "on_role_change" {
         std::process::exit(0);
     }
 
-    let node_name = env::var("PATRONI_NAME").unwrap_or_else(|_| "unknown".to_string());
-    let node_address =
-        env::var("RAILWAY_PRIVATE_DOMAIN").unwrap_or_else(|_| "unknown".to_string());
-    let project_id = env::var("RAILWAY_PROJECT_ID").unwrap_or_default();
-    let environment_id = env::var("RAILWAY_ENVIRONMENT_ID").unwrap_or_default();
-    let service_id = env::var("RAILWAY_SERVICE_ID").unwrap_or_default();
-
-    let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
-
-    // Determine event type based on new role
-    let (event_type, message) = match role {
-        "master" | "primary" => (
-            "POSTGRES_HA_FAILOVER",
-            "Node promoted to primary (failover completed)",
-        ),
-        "replica" | "standby" => ("POSTGRES_HA_REJOINED", "Node rejoined cluster as replica"),
-        _ => ("POSTGRES_HA_ROLE_CHANGE", "Node role changed"),
+    let event = RoleChangeEvent {
+        event_type: match role {
+            "master" | "primary" => "POSTGRES_HA_FAILOVER",
+            "replica" | "standby" => "POSTGRES_HA_REJOINED",
+            _ => "POSTGRES_HA_ROLE_CHANGE",
+        },
+        message: match role {
+            "master" | "primary" => "Node promoted to primary (failover completed)",
+            "replica" | "standby" => "Node rejoined cluster as replica",
+            _ => "Node role changed",
+        },
+        node: env::var("PATRONI_NAME").unwrap_or_else(|_| "unknown".to_string()),
+        scope: scope.to_string(),
+        role: role.to_string(),
+        service_id: env::var("RAILWAY_SERVICE_ID").unwrap_or_default(),
+        project_id: env::var("RAILWAY_PROJECT_ID").unwrap_or_default(),
+        environment_id: env::var("RAILWAY_ENVIRONMENT_ID").unwrap_or_default(),
+        timestamp: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
     };
 
     // Log locally for container logs
     println!(
         "[{}] {}: {} (node={}, scope={}, service={})",
-        timestamp, event_type, message, node_name, scope, service_id
+        event.timestamp, event.event_type, event.message, event.node, event.scope, event.service_id
     );
 
-    let metadata = format!(
-        "node={}, role={}, scope={}, address={}, serviceId={}, projectId={}, environmentId={}",
-        node_name, role, scope, node_address, service_id, project_id, environment_id
-    );
+    let sinks = configured_sinks();
 
-    let graphql_endpoint = env::var("RAILWAY_GRAPHQL_ENDPOINT")
-        .unwrap_or_else(|_| "https://backboard.railway.app/graphql/internal".to_string());
-
-    let payload = serde_json::json!({
-        "query": "mutation telemetrySend($input: TelemetrySendInput!) { telemetrySend(input: $input) }",
-        "variables": {
-            "input": {
-                "command": event_type,
-                "error": message,
-                "stacktrace": metadata,
-                "projectId": project_id,
-                "environmentId": environment_id,
-                "version": "postgres-ha"
-            }
-        }
-    });
-
-    // Send telemetry asynchronously (fire and forget)
-    // Use a short timeout to not block Patroni
-    let _ = std::thread::spawn(move || {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(5))
-            .build();
-
-        if let Ok(client) = client {
-            let _ = client
-                .post(&graphql_endpoint)
-                .header("Content-Type", "application/json")
-                .json(&payload)
-                .send();
+    // Fan out to every sink in parallel, each with its own retry budget, so
+    // one slow/unreachable sink can't delay the others. Fire-and-forget:
+    // Patroni's callback pipeline must never wait on this.
+    let handles: Vec<_> = sinks
+        .into_iter()
+        .map(|sink| {
+            let event_for_thread = event.clone();
+            std::thread::spawn(move || sink.send(&event_for_thread, ALERT_BUDGET))
+        })
+        .collect();
+
+    // Give sinks a chance to land, but never block Patroni past the budget.
+    let join_deadline = std::time::Instant::now() + ALERT_BUDGET;
+    for handle in handles {
+        if std::time::Instant::now() >= join_deadline {
+            break;
         }
-    });
+        let _ = handle.join();
+    }
 
     // Always exit 0 to not block Patroni
     std::process::exit(0);
@@ -4,26 +4,65 @@
 //! Runs as PID 1 in container with built-in health monitoring.
 //! If Patroni dies or becomes unresponsive, exits to trigger container restart.
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use common::{Telemetry, TelemetryEvent};
 use nix::sys::signal::{kill, Signal};
 use nix::unistd::Pid;
+use postgres_patroni::config_template::{self, ConfigOverrides};
+use postgres_patroni::encrypted_secrets::{EncryptedSecrets, KdfAlgorithm};
+use postgres_patroni::maintenance;
+use postgres_patroni::metrics::{self, MetricsConfig};
+use postgres_patroni::pgbackrest::{self, BackupConfig};
+use postgres_patroni::secrets;
+use postgres_patroni::upgrade;
 use postgres_patroni::{pgdata, ssl_dir, volume_root};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
 use std::env;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Stdio;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::process::{Child, Command};
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::time::sleep;
 use tracing::{error, info, warn};
 
+/// Which distributed consensus store Patroni uses to coordinate the
+/// cluster. `Raft` (python-pysyncobj) lets the Postgres nodes form their
+/// own consensus ring without a separate etcd quorum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DcsBackend {
+    Etcd3,
+    Raft,
+}
+
+impl FromStr for DcsBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "etcd3" => Ok(Self::Etcd3),
+            "raft" => Ok(Self::Raft),
+            other => Err(anyhow!(
+                "PATRONI_DCS_BACKEND must be 'etcd3' or 'raft', got '{}'",
+                other
+            )),
+        }
+    }
+}
+
 struct Config {
     scope: String,
     name: String,
     connect_address: String,
-    etcd_hosts: String,
+    dcs_backend: DcsBackend,
+    etcd_hosts: Option<String>,
+    raft_self_addr: Option<String>,
+    raft_partner_addrs: Option<String>,
+    raft_data_dir: Option<String>,
     superuser: String,
     superuser_pass: String,
     repl_user: String,
@@ -39,31 +78,107 @@ struct Config {
     health_check_interval: u64,
     health_check_timeout: u64,
     max_failures: u32,
-    startup_grace_period: u64,
+    max_replication_lag_bytes: u64,
+    max_startup_timeout: u64,
+    shutdown_switchover_timeout: u64,
+    api_port: u16,
+    maintenance_window: Option<String>,
+    maintenance_action: maintenance::MaintenanceAction,
     adopt_existing_data: bool,
+    allow_major_upgrade: bool,
+    synchronous_mode: bool,
+    synchronous_mode_strict: bool,
+    synchronous_node_count: u32,
+    expected_replicas: Option<u32>,
+    metrics_enabled: bool,
+    metrics_port: u16,
+    backup_enabled: bool,
+    backup_repo_type: String,
+    backup_repo_path: String,
+    backup_repo_s3_bucket: Option<String>,
+    backup_repo_s3_endpoint: Option<String>,
+    backup_repo_s3_region: Option<String>,
+    backup_repo_s3_key: Option<String>,
+    backup_repo_s3_key_secret: Option<String>,
 }
 
 impl Config {
-    fn from_env() -> Result<Self> {
+    async fn from_env() -> Result<Self> {
         let name = env::var("PATRONI_NAME").context("PATRONI_NAME must be set")?;
         let connect_address =
             env::var("RAILWAY_PRIVATE_DOMAIN").context("RAILWAY_PRIVATE_DOMAIN must be set")?;
-        let etcd_hosts =
-            env::var("PATRONI_ETCD3_HOSTS").context("PATRONI_ETCD3_HOSTS must be set")?;
+
+        let dcs_backend = env::var("PATRONI_DCS_BACKEND")
+            .unwrap_or_else(|_| "etcd3".to_string())
+            .parse::<DcsBackend>()?;
+
+        let (etcd_hosts, raft_self_addr, raft_partner_addrs, raft_data_dir) = match dcs_backend {
+            DcsBackend::Etcd3 => {
+                let hosts = env::var("PATRONI_ETCD3_HOSTS").context(
+                    "PATRONI_ETCD3_HOSTS must be set when PATRONI_DCS_BACKEND=etcd3",
+                )?;
+                (Some(hosts), None, None, None)
+            }
+            DcsBackend::Raft => {
+                let self_addr = env::var("PATRONI_RAFT_SELF_ADDR").context(
+                    "PATRONI_RAFT_SELF_ADDR must be set when PATRONI_DCS_BACKEND=raft",
+                )?;
+                let partner_addrs = env::var("PATRONI_RAFT_PARTNER_ADDRS").context(
+                    "PATRONI_RAFT_PARTNER_ADDRS must be set when PATRONI_DCS_BACKEND=raft",
+                )?;
+                (None, Some(self_addr), Some(partner_addrs), env::var("PATRONI_RAFT_DATA_DIR").ok())
+            }
+        };
+
+        // Resolve passwords through the file/DCS secret-sourcing layer before
+        // falling back to the literal env vars, so deployments can mount
+        // secrets or store them in etcd instead of baking them into the
+        // container env or image layers.
+        let superuser_pass = secrets::resolve(
+            env::var("PATRONI_SUPERUSER_PASSWORD").ok(),
+            "PATRONI_SUPERUSER_PASSWORD_FILE",
+            "secrets/patroni/superuser_password",
+            etcd_hosts.as_deref(),
+        )
+        .await
+        .context("failed to resolve superuser password")?
+        .unwrap_or_default();
+        let repl_pass = secrets::resolve(
+            env::var("PATRONI_REPLICATION_PASSWORD").ok(),
+            "PATRONI_REPLICATION_PASSWORD_FILE",
+            "secrets/patroni/replication_password",
+            etcd_hosts.as_deref(),
+        )
+        .await
+        .context("failed to resolve replication password")?
+        .unwrap_or_default();
+        let app_pass = secrets::resolve(
+            env::var("POSTGRES_PASSWORD").ok(),
+            "POSTGRES_PASSWORD_FILE",
+            "secrets/patroni/app_password",
+            etcd_hosts.as_deref(),
+        )
+        .await
+        .context("failed to resolve app password")?
+        .unwrap_or_default();
 
         Ok(Self {
             scope: env::var("PATRONI_SCOPE").unwrap_or_else(|_| "railway-pg-ha".to_string()),
             name,
             connect_address,
+            dcs_backend,
             etcd_hosts,
+            raft_self_addr,
+            raft_partner_addrs,
+            raft_data_dir,
             superuser: env::var("PATRONI_SUPERUSER_USERNAME")
                 .unwrap_or_else(|_| "postgres".to_string()),
-            superuser_pass: env::var("PATRONI_SUPERUSER_PASSWORD").unwrap_or_default(),
+            superuser_pass,
             repl_user: env::var("PATRONI_REPLICATION_USERNAME")
                 .unwrap_or_else(|_| "replicator".to_string()),
-            repl_pass: env::var("PATRONI_REPLICATION_PASSWORD").unwrap_or_default(),
+            repl_pass,
             app_user: env::var("POSTGRES_USER").unwrap_or_else(|_| "postgres".to_string()),
-            app_pass: env::var("POSTGRES_PASSWORD").unwrap_or_default(),
+            app_pass,
             app_db: env::var("POSTGRES_DB")
                 .or_else(|_| env::var("PGDATABASE"))
                 .unwrap_or_else(|_| "railway".to_string()),
@@ -86,114 +201,235 @@ impl Config {
                 .unwrap_or_else(|_| "3".to_string())
                 .parse()
                 .unwrap_or(3),
-            startup_grace_period: env::var("PATRONI_STARTUP_GRACE_PERIOD")
+            max_replication_lag_bytes: env::var("PATRONI_MAX_REPLICATION_LAG_BYTES")
+                .unwrap_or_else(|_| (100 * 1024 * 1024).to_string())
+                .parse()
+                .unwrap_or(100 * 1024 * 1024),
+            max_startup_timeout: env::var("PATRONI_MAX_STARTUP_TIMEOUT")
                 .unwrap_or_else(|_| "60".to_string())
                 .parse()
                 .unwrap_or(60),
+            shutdown_switchover_timeout: env::var("PATRONI_SHUTDOWN_SWITCHOVER_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            api_port: env::var("PATRONI_API_PORT")
+                .unwrap_or_else(|_| "8008".to_string())
+                .parse()
+                .unwrap_or(8008),
+            maintenance_window: env::var("PATRONI_MAINTENANCE_WINDOW").ok(),
+            maintenance_action: env::var("PATRONI_MAINTENANCE_ACTION")
+                .ok()
+                .and_then(|v| maintenance::MaintenanceAction::parse(&v).ok())
+                .unwrap_or(maintenance::MaintenanceAction::Restart),
             adopt_existing_data: env::var("PATRONI_ADOPT_EXISTING_DATA")
                 .map(|v| v.to_lowercase() == "true")
                 .unwrap_or(false),
+            allow_major_upgrade: env::var("PATRONI_ALLOW_MAJOR_UPGRADE")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            synchronous_mode: env::var("PATRONI_SYNCHRONOUS_MODE")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            synchronous_mode_strict: env::var("PATRONI_SYNCHRONOUS_MODE_STRICT")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            synchronous_node_count: env::var("PATRONI_SYNCHRONOUS_NODE_COUNT")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(1),
+            expected_replicas: env::var("PATRONI_EXPECTED_REPLICAS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            metrics_enabled: env::var("PATRONI_METRICS_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            metrics_port: env::var("PATRONI_METRICS_PORT")
+                .unwrap_or_else(|_| "8009".to_string())
+                .parse()
+                .unwrap_or(8009),
+            backup_enabled: env::var("PATRONI_BACKUP_ENABLED")
+                .map(|v| v.to_lowercase() == "true")
+                .unwrap_or(false),
+            backup_repo_type: env::var("PATRONI_BACKUP_REPO_TYPE")
+                .unwrap_or_else(|_| "local".to_string()),
+            backup_repo_path: env::var("PATRONI_BACKUP_REPO_PATH")
+                .unwrap_or_else(|_| "/var/lib/pgbackrest".to_string()),
+            backup_repo_s3_bucket: env::var("PATRONI_BACKUP_REPO_S3_BUCKET").ok(),
+            backup_repo_s3_endpoint: env::var("PATRONI_BACKUP_REPO_S3_ENDPOINT").ok(),
+            backup_repo_s3_region: env::var("PATRONI_BACKUP_REPO_S3_REGION").ok(),
+            backup_repo_s3_key: env::var("PATRONI_BACKUP_REPO_S3_KEY").ok(),
+            backup_repo_s3_key_secret: env::var("PATRONI_BACKUP_REPO_S3_KEY_SECRET").ok(),
         })
     }
 }
 
-fn generate_patroni_config(config: &Config) -> String {
-    format!(
-        r#"scope: {scope}
-name: {name}
-
-restapi:
-  listen: 0.0.0.0:8008
-  connect_address: {connect_address}:8008
-
-etcd3:
-  hosts: {etcd_hosts}
-
-bootstrap:
-  dcs:
-    ttl: {ttl}
-    loop_wait: {loop_wait}
-    retry_timeout: {retry_timeout}
-    maximum_lag_on_failover: 1048576
-    failsafe_mode: true
-    postgresql:
-      use_pg_rewind: true
-      use_slots: true
-      parameters:
-        wal_level: replica
-        hot_standby: "on"
-        max_wal_senders: 10
-        max_replication_slots: 10
-        max_connections: 200
-        password_encryption: scram-sha-256
-
-  initdb:
-    - encoding: UTF8
-    - data-checksums
-    - username: {superuser}
-
-  pg_hba:
-    - local all all trust
-    - hostssl replication {repl_user} 0.0.0.0/0 scram-sha-256
-    - hostssl replication {repl_user} ::/0 scram-sha-256
-    - hostssl all all 0.0.0.0/0 scram-sha-256
-    - hostssl all all ::/0 scram-sha-256
-    - host replication {repl_user} 0.0.0.0/0 scram-sha-256
-    - host replication {repl_user} ::/0 scram-sha-256
-    - host all all 0.0.0.0/0 scram-sha-256
-    - host all all ::/0 scram-sha-256
-
-  post_bootstrap: /post_bootstrap.sh
-
-postgresql:
-  listen: "*:5432"
-  connect_address: {connect_address}:5432
-  data_dir: {data_dir}
-  pgpass: /tmp/pgpass
-  callbacks:
-    on_role_change: /on_role_change.sh
-  remove_data_directory_on_rewind_failure: true
-  remove_data_directory_on_diverged_timelines: true
-  create_replica_methods:
-    - basebackup
-  basebackup:
-    checkpoint: "fast"
-    wal-method: "stream"
-  authentication:
-    replication:
-      username: "{repl_user}"
-      password: "{repl_pass}"
-    superuser:
-      username: "{superuser}"
-      password: "{superuser_pass}"
-  app_user:
-    username: "{app_user}"
-    password: "{app_pass}"
-    database: "{app_db}"
-  parameters:
-    unix_socket_directories: /var/run/postgresql
-    ssl: "on"
-    ssl_cert_file: "{certs_dir}/server.crt"
-    ssl_key_file: "{certs_dir}/server.key"
-    ssl_ca_file: "{certs_dir}/root.crt"
-"#,
-        scope = config.scope,
-        name = config.name,
-        connect_address = config.connect_address,
-        etcd_hosts = config.etcd_hosts,
-        ttl = config.ttl,
-        loop_wait = config.loop_wait,
-        retry_timeout = config.retry_timeout,
-        superuser = config.superuser,
-        superuser_pass = config.superuser_pass,
-        repl_user = config.repl_user,
-        repl_pass = config.repl_pass,
-        app_user = config.app_user,
-        app_pass = config.app_pass,
-        app_db = config.app_db,
-        data_dir = config.data_dir,
-        certs_dir = config.certs_dir,
-    )
+/// Warn when a synchronous replication config can never be satisfied, which
+/// would otherwise leave the primary permanently blocked on commit (in
+/// strict mode) or silently running async.
+fn validate_synchronous_replication(config: &Config) {
+    if config.synchronous_node_count == 0 {
+        return;
+    }
+
+    if let Some(replicas) = config.expected_replicas {
+        if replicas < config.synchronous_node_count {
+            warn!(
+                synchronous_node_count = config.synchronous_node_count,
+                expected_replicas = replicas,
+                "PATRONI_SYNCHRONOUS_NODE_COUNT exceeds PATRONI_EXPECTED_REPLICAS; \
+                 synchronous replication can never be satisfied and the primary \
+                 may deadlock on commit in strict mode"
+            );
+        }
+    }
+}
+
+/// Template context mirroring the field names used in the base Handlebars
+/// template (see `config_template::render`).
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    scope: &'a str,
+    name: &'a str,
+    connect_address: &'a str,
+    api_port: u16,
+    is_raft: bool,
+    etcd_hosts: &'a str,
+    raft_self_addr: &'a str,
+    raft_partner_addrs: &'a str,
+    raft_data_dir: &'a str,
+    ttl: &'a str,
+    loop_wait: &'a str,
+    retry_timeout: &'a str,
+    synchronous_mode: bool,
+    synchronous_mode_strict: bool,
+    synchronous_node_count: u32,
+    superuser: &'a str,
+    superuser_pass: &'a str,
+    repl_user: &'a str,
+    repl_pass: &'a str,
+    app_user: &'a str,
+    app_pass: &'a str,
+    app_db: &'a str,
+    data_dir: &'a str,
+    certs_dir: &'a str,
+    backup_enabled: bool,
+}
+
+/// Load user-supplied config overrides from the `PATRONI_CONFIG_OVERRIDES`
+/// env var (a YAML document with `postgresql_parameters`,
+/// `dcs_postgresql_parameters` and `extra_pg_hba` keys) if set.
+fn load_overrides() -> Result<ConfigOverrides> {
+    let mut overrides = ConfigOverrides::default();
+
+    if let Ok(raw) = env::var("PATRONI_CONFIG_OVERRIDES") {
+        let parsed: Value =
+            serde_yaml::from_str(&raw).context("Failed to parse PATRONI_CONFIG_OVERRIDES")?;
+
+        if let Some(map) = parsed.as_mapping() {
+            if let Some(v) = map.get(Value::String("postgresql_parameters".into())) {
+                overrides.postgresql_parameters = v.clone();
+            }
+            if let Some(v) = map.get(Value::String("dcs_postgresql_parameters".into())) {
+                overrides.dcs_postgresql_parameters = v.clone();
+            }
+            if let Some(Value::Sequence(lines)) =
+                map.get(Value::String("extra_pg_hba".into()))
+            {
+                overrides.extra_pg_hba = lines
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect();
+            }
+        }
+    }
+
+    Ok(overrides)
+}
+
+/// Render `PATRONI_RAFT_PARTNER_ADDRS` (comma-separated) as a YAML flow
+/// sequence, e.g. `"a:5010,b:5010"` -> `"[a:5010, b:5010]"`.
+fn raft_partner_addrs_yaml(raw: &str) -> String {
+    let items: Vec<&str> = raw.split(',').map(str::trim).filter(|a| !a.is_empty()).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// When `PATRONI_SECRETS_PASSPHRASE` is set, bootstraps a fresh
+/// `EncryptedSecrets` and seals the three passwords under it, returning the
+/// sealed passwords plus the `encryption:` section to write alongside them.
+/// Returns the passwords unchanged and `None` when the passphrase isn't set,
+/// so the rendered config stays plaintext exactly as before this existed.
+fn maybe_encrypt_passwords(
+    superuser_pass: &str,
+    repl_pass: &str,
+    app_pass: &str,
+) -> Result<(String, String, String, Option<Value>)> {
+    let passphrase = match env::var("PATRONI_SECRETS_PASSPHRASE") {
+        Ok(p) if !p.is_empty() => p,
+        _ => return Ok((superuser_pass.to_string(), repl_pass.to_string(), app_pass.to_string(), None)),
+    };
+
+    let (secrets, kdf, salt, verify_blob) = EncryptedSecrets::bootstrap(KdfAlgorithm::Argon2id, &passphrase)
+        .context("Failed to bootstrap secrets-at-rest encryption")?;
+
+    let sealed_superuser_pass = secrets.seal(superuser_pass);
+    let sealed_repl_pass = secrets.seal(repl_pass);
+    let sealed_app_pass = if app_pass.is_empty() {
+        String::new()
+    } else {
+        secrets.seal(app_pass)
+    };
+
+    let mut encryption = Mapping::new();
+    encryption.insert(Value::String("kdf".into()), Value::String(kdf.to_string()));
+    encryption.insert(Value::String("salt".into()), Value::String(salt));
+    encryption.insert(Value::String("verify_blob".into()), Value::String(verify_blob));
+
+    Ok((sealed_superuser_pass, sealed_repl_pass, sealed_app_pass, Some(Value::Mapping(encryption))))
+}
+
+fn generate_patroni_config(config: &Config) -> Result<String> {
+    let is_raft = config.dcs_backend == DcsBackend::Raft;
+    let raft_partner_addrs = config
+        .raft_partner_addrs
+        .as_deref()
+        .map(raft_partner_addrs_yaml)
+        .unwrap_or_default();
+
+    let (superuser_pass, repl_pass, app_pass, encryption) =
+        maybe_encrypt_passwords(&config.superuser_pass, &config.repl_pass, &config.app_pass)?;
+
+    let ctx = TemplateContext {
+        scope: &config.scope,
+        name: &config.name,
+        connect_address: &config.connect_address,
+        api_port: config.api_port,
+        is_raft,
+        etcd_hosts: config.etcd_hosts.as_deref().unwrap_or(""),
+        raft_self_addr: config.raft_self_addr.as_deref().unwrap_or(""),
+        raft_partner_addrs: &raft_partner_addrs,
+        raft_data_dir: config.raft_data_dir.as_deref().unwrap_or(""),
+        ttl: &config.ttl,
+        loop_wait: &config.loop_wait,
+        retry_timeout: &config.retry_timeout,
+        synchronous_mode: config.synchronous_mode,
+        synchronous_mode_strict: config.synchronous_mode_strict,
+        synchronous_node_count: config.synchronous_node_count,
+        superuser: &config.superuser,
+        superuser_pass: &superuser_pass,
+        repl_user: &config.repl_user,
+        repl_pass: &repl_pass,
+        app_user: &config.app_user,
+        app_pass: &app_pass,
+        app_db: &config.app_db,
+        data_dir: &config.data_dir,
+        certs_dir: &config.certs_dir,
+        backup_enabled: config.backup_enabled,
+    };
+
+    let mut overrides = load_overrides()?;
+    overrides.encryption = encryption;
+    config_template::render(&ctx, &overrides)
 }
 
 fn update_pg_hba_for_replication(config: &Config) -> Result<()> {
@@ -210,10 +446,25 @@ fn update_pg_hba_for_replication(config: &Config) -> Result<()> {
 
     let content = fs::read_to_string(&pg_hba_path)?;
 
-    // Check if replication entries exist for our specific user
-    if content.contains(&format!("replication {}", config.repl_user))
-        || content.contains(&format!("replication\t{}", config.repl_user))
-    {
+    // Parse into structural entries (whitespace-split fields) so we dedupe
+    // by the actual "replication user" field instead of substring-matching
+    // the raw text, which can false-positive on a comment or a different
+    // column containing the username.
+    let existing_repl_users: std::collections::HashSet<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let fields: Vec<&str> = l.split_whitespace().collect();
+            if fields.len() >= 3 && fields[1] == "replication" {
+                Some(fields[2])
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if existing_repl_users.contains(config.repl_user.as_str()) {
         info!(
             "Replication entries for {} already exist in pg_hba.conf",
             config.repl_user
@@ -256,23 +507,180 @@ host replication {} ::/0 scram-sha-256
     Ok(())
 }
 
-async fn check_health(timeout_secs: u64) -> bool {
-    let client = match reqwest::Client::builder()
-        .timeout(Duration::from_secs(timeout_secs))
-        .build()
-    {
-        Ok(c) => c,
-        Err(_) => return false,
-    };
-
+async fn check_health(client: &reqwest::Client, api_port: u16) -> bool {
     client
-        .get("http://localhost:8008/health")
+        .get(format!("http://localhost:{api_port}/health"))
         .send()
         .await
         .map(|r| r.status().is_success())
         .unwrap_or(false)
 }
 
+#[derive(Debug, serde::Deserialize, Default)]
+struct ReplicaState {
+    state: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct PatroniState {
+    role: Option<String>,
+    #[serde(default)]
+    replication: Vec<ReplicaState>,
+}
+
+fn is_leader_role(role: &str) -> bool {
+    matches!(role, "master" | "leader" | "primary" | "standby_leader")
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct ClusterMember {
+    name: String,
+    role: String,
+    #[serde(default)]
+    timeline: Option<u64>,
+    #[serde(default)]
+    lag: Option<serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct ClusterStatus {
+    #[serde(default)]
+    members: Vec<ClusterMember>,
+}
+
+/// This node's lag (in bytes) and timeline relative to the leader, read from
+/// Patroni's `/cluster` view - any member can serve this from its cached DCS
+/// data, so it doesn't require talking to the leader directly. `None` if
+/// this node is the leader itself or isn't present in the member list.
+struct ReplicaLagStatus {
+    lag_bytes: Option<u64>,
+    local_timeline: Option<u64>,
+    leader_timeline: Option<u64>,
+    timeline_diverged: bool,
+}
+
+async fn check_replica_lag(client: &reqwest::Client, api_port: u16, node_name: &str) -> Option<ReplicaLagStatus> {
+    let cluster: ClusterStatus = client
+        .get(format!("http://localhost:{api_port}/cluster"))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+
+    let leader_timeline = cluster
+        .members
+        .iter()
+        .find(|m| is_leader_role(&m.role))
+        .and_then(|m| m.timeline);
+    let me = cluster.members.iter().find(|m| m.name == node_name)?;
+
+    if is_leader_role(&me.role) {
+        return None;
+    }
+
+    let lag_bytes = me.lag.as_ref().and_then(|v| v.as_u64());
+    let timeline_diverged = matches!((leader_timeline, me.timeline), (Some(lt), Some(mt)) if lt != mt);
+
+    Some(ReplicaLagStatus {
+        lag_bytes,
+        local_timeline: me.timeline,
+        leader_timeline,
+        timeline_diverged,
+    })
+}
+
+/// Ask Patroni to rebuild this replica from a fresh basebackup.
+async fn reinitialize(client: &reqwest::Client, api_port: u16) -> Result<()> {
+    let resp = client
+        .post(format!("http://localhost:{api_port}/reinitialize"))
+        .send()
+        .await
+        .context("reinitialize request failed")?;
+
+    if !resp.status().is_success() {
+        bail!("reinitialize request returned {}", resp.status());
+    }
+
+    Ok(())
+}
+
+async fn fetch_patroni_state(client: &reqwest::Client, api_port: u16) -> Option<PatroniState> {
+    client
+        .get(format!("http://localhost:{api_port}/patroni"))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// If this node is currently the leader and has at least one streaming
+/// replica, ask Patroni to switch the leadership over to a replica before
+/// shutdown instead of letting the primary just disappear. Returns once the
+/// role has flipped away from leader, or after `timeout_secs` elapses -
+/// either way the caller proceeds to stop Patroni afterward. Returns
+/// `false` (leaving the caller's immediate-shutdown behavior unchanged) if
+/// this node isn't the leader, has no healthy candidate, or the switchover
+/// request itself fails.
+async fn attempt_graceful_switchover(
+    client: &reqwest::Client,
+    api_port: u16,
+    name: &str,
+    timeout_secs: u64,
+) -> bool {
+    let Some(state) = fetch_patroni_state(client, api_port).await else {
+        return false;
+    };
+
+    let role = state.role.unwrap_or_default();
+    if !is_leader_role(&role) {
+        return false;
+    }
+
+    let has_streaming_replica = state
+        .replication
+        .iter()
+        .any(|r| r.state.as_deref() == Some("streaming"));
+    if !has_streaming_replica {
+        info!("Leader has no streaming replica, skipping graceful switchover");
+        return false;
+    }
+
+    info!(node = %name, "Leader shutting down with a healthy replica available, attempting graceful switchover");
+
+    let body = serde_json::json!({ "leader": name });
+    if let Err(e) = client
+        .post(format!("http://localhost:{api_port}/switchover"))
+        .json(&body)
+        .send()
+        .await
+    {
+        warn!(error = %e, "Switchover request failed, falling back to immediate shutdown");
+        return false;
+    }
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+    while tokio::time::Instant::now() < deadline {
+        sleep(Duration::from_secs(1)).await;
+
+        if let Some(state) = fetch_patroni_state(client, api_port).await {
+            if !is_leader_role(&state.role.unwrap_or_default()) {
+                info!("Switchover complete, shutting down former leader");
+                return true;
+            }
+        }
+    }
+
+    warn!(
+        timeout_secs,
+        "Switchover did not complete in time, shutting down anyway"
+    );
+    false
+}
+
 async fn start_patroni() -> Result<Child> {
     let child = Command::new("patroni")
         .arg("/tmp/patroni.yml")
@@ -298,7 +706,39 @@ async fn main() -> Result<()> {
 
     info!("=== Patroni Runner ===");
 
-    let config = Config::from_env()?;
+    common::merge_dotenv()?;
+
+    let config = Config::from_env().await?;
+    validate_synchronous_replication(&config);
+    let telemetry = Telemetry::from_env("postgres-ha");
+
+    let maintenance_schedule = config
+        .maintenance_window
+        .as_deref()
+        .map(maintenance::Schedule::parse)
+        .transpose()
+        .context("invalid PATRONI_MAINTENANCE_WINDOW")?;
+
+    // Shared by the health-check loop below and the metrics scrape loop, so
+    // polling Patroni's REST API for either purpose reuses one connection
+    // pool instead of each dialing independently.
+    let health_client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.health_check_timeout))
+        .build()
+        .context("Failed to build Patroni health check client")?;
+
+    if config.metrics_enabled {
+        metrics::spawn(MetricsConfig {
+            enabled: true,
+            port: config.metrics_port,
+            scope: config.scope.clone(),
+            node_name: config.name.clone(),
+            api_port: config.api_port,
+            scrape_interval: Duration::from_secs(config.health_check_interval),
+            client: health_client.clone(),
+        });
+        info!(port = config.metrics_port, "Prometheus metrics exporter enabled");
+    }
 
     info!(
         "Node: {} (address: {})",
@@ -330,14 +770,57 @@ async fn main() -> Result<()> {
         info!("No PostgreSQL data found");
     }
 
+    // Only a node with its own local data can be on an older major than the
+    // installed binaries - a replica with no data here just re-syncs from the
+    // (now upgraded) leader via basebackup once Patroni starts.
+    if has_pg_control {
+        if let Err(e) = upgrade::maybe_upgrade(&config.data_dir, config.allow_major_upgrade, &telemetry, &config.name).await {
+            error!(error = %e, "Major version upgrade failed, exiting for recovery");
+            std::process::exit(1);
+        }
+        if !has_marker {
+            fs::write(&bootstrap_marker, "").context("Failed to create bootstrap marker")?;
+        }
+    }
+
+    // Set up pgBackRest ahead of generating patroni.yml, since the rendered
+    // create_replica_methods/pgbackrest block assumes the stanza is ready.
+    if config.backup_enabled {
+        let backup_config = BackupConfig {
+            stanza: config.scope.clone(),
+            repo_type: config.backup_repo_type.clone(),
+            repo_path: config.backup_repo_path.clone(),
+            repo_s3_bucket: config.backup_repo_s3_bucket.clone(),
+            repo_s3_endpoint: config.backup_repo_s3_endpoint.clone(),
+            repo_s3_region: config.backup_repo_s3_region.clone(),
+            repo_s3_key: config.backup_repo_s3_key.clone(),
+            repo_s3_key_secret: config.backup_repo_s3_key_secret.clone(),
+            pg_data_dir: config.data_dir.clone(),
+        };
+        pgbackrest::write_config(&backup_config).context("Failed to write pgbackrest.conf")?;
+        if let Err(e) = pgbackrest::ensure_stanza(&config.scope).await {
+            warn!(error = %e, "pgbackrest stanza-create failed, continuing without it");
+        }
+    }
+
     // Generate Patroni configuration
-    let patroni_config = generate_patroni_config(&config);
+    let patroni_config = generate_patroni_config(&config)?;
     fs::write("/tmp/patroni.yml", &patroni_config).context("Failed to write patroni.yml")?;
-
-    info!(
-        "Starting Patroni (scope: {}, etcd: {})",
-        config.scope, config.etcd_hosts
-    );
+    fs::set_permissions("/tmp/patroni.yml", std::fs::Permissions::from_mode(0o600))
+        .context("Failed to restrict patroni.yml permissions")?;
+
+    match config.dcs_backend {
+        DcsBackend::Etcd3 => info!(
+            "Starting Patroni (scope: {}, etcd: {})",
+            config.scope,
+            config.etcd_hosts.as_deref().unwrap_or("")
+        ),
+        DcsBackend::Raft => info!(
+            "Starting Patroni (scope: {}, raft self_addr: {})",
+            config.scope,
+            config.raft_self_addr.as_deref().unwrap_or("")
+        ),
+    }
 
     // Ensure data directory has correct permissions
     fs::create_dir_all(&config.data_dir).ok();
@@ -360,23 +843,38 @@ async fn main() -> Result<()> {
     let mut sigterm = signal(SignalKind::terminate())?;
     let mut sigint = signal(SignalKind::interrupt())?;
 
-    // Wait for startup grace period
+    // Block until the Patroni REST API is actually accepting connections and
+    // reporting healthy, rather than assuming it's up after a fixed sleep.
     info!(
-        "Waiting {}s for Patroni to initialize...",
-        config.startup_grace_period
+        "Waiting for Patroni REST API readiness on port {} (timeout={}s, interval={}s)...",
+        config.api_port, config.max_startup_timeout, config.health_check_interval
     );
 
     let mut startup_elapsed = 0u64;
-    while startup_elapsed < config.startup_grace_period {
+    let mut consecutive_failures = 0u32;
+    loop {
+        if startup_elapsed >= config.max_startup_timeout {
+            error!(
+                "CRITICAL: Patroni REST API not ready after {}s - exiting to trigger restart",
+                config.max_startup_timeout
+            );
+            let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
+            sleep(Duration::from_secs(2)).await;
+            let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGKILL);
+            std::process::exit(1);
+        }
+
         tokio::select! {
             _ = sigterm.recv() => {
                 info!("Received SIGTERM during startup, stopping Patroni...");
+                attempt_graceful_switchover(&health_client, config.api_port, &config.name, config.shutdown_switchover_timeout).await;
                 let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
                 let _ = child.wait().await;
                 return Ok(());
             }
             _ = sigint.recv() => {
                 info!("Received SIGINT during startup, stopping Patroni...");
+                attempt_graceful_switchover(&health_client, config.api_port, &config.name, config.shutdown_switchover_timeout).await;
                 let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
                 let _ = child.wait().await;
                 return Ok(());
@@ -385,20 +883,36 @@ async fn main() -> Result<()> {
                 error!("Patroni process died during startup");
                 std::process::exit(1);
             }
-            _ = sleep(Duration::from_secs(5)) => {
-                startup_elapsed += 5;
+            _ = sleep(Duration::from_secs(config.health_check_interval)) => {
+                startup_elapsed += config.health_check_interval;
 
-                // Try health check early
-                if check_health(config.health_check_timeout).await {
+                if check_health(&health_client, config.api_port).await {
                     info!("Patroni healthy after {}s, starting health monitoring", startup_elapsed);
                     break;
                 }
+
+                consecutive_failures += 1;
+                warn!(
+                    "Startup health check failed ({}/{})",
+                    consecutive_failures, config.max_failures
+                );
+                if consecutive_failures >= config.max_failures {
+                    error!(
+                        "CRITICAL: Patroni REST API failed {} consecutive startup checks - exiting to trigger restart",
+                        consecutive_failures
+                    );
+                    let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
+                    sleep(Duration::from_secs(2)).await;
+                    let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGKILL);
+                    std::process::exit(1);
+                }
             }
         }
     }
 
     // Main health monitoring loop
     let mut failures = 0u32;
+    let mut lag_failures = 0u32;
     info!(
         "Health monitoring active (interval={}s, max_failures={})",
         config.health_check_interval, config.max_failures
@@ -408,12 +922,14 @@ async fn main() -> Result<()> {
         tokio::select! {
             _ = sigterm.recv() => {
                 info!("Received SIGTERM, stopping Patroni...");
+                attempt_graceful_switchover(&health_client, config.api_port, &config.name, config.shutdown_switchover_timeout).await;
                 let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
                 let _ = child.wait().await;
                 return Ok(());
             }
             _ = sigint.recv() => {
                 info!("Received SIGINT, stopping Patroni...");
+                attempt_graceful_switchover(&health_client, config.api_port, &config.name, config.shutdown_switchover_timeout).await;
                 let _ = kill(Pid::from_raw(patroni_pid as i32), Signal::SIGTERM);
                 let _ = child.wait().await;
                 return Ok(());
@@ -423,11 +939,52 @@ async fn main() -> Result<()> {
                 std::process::exit(1);
             }
             _ = sleep(Duration::from_secs(config.health_check_interval)) => {
-                if check_health(config.health_check_timeout).await {
+                if check_health(&health_client, config.api_port).await {
                     if failures > 0 {
                         info!("Patroni recovered after {} failed health checks", failures);
                     }
                     failures = 0;
+
+                    if let Some(lag_status) = check_replica_lag(&health_client, config.api_port, &config.name).await {
+                        if lag_status.timeline_diverged {
+                            warn!(
+                                local_timeline = ?lag_status.local_timeline,
+                                leader_timeline = ?lag_status.leader_timeline,
+                                "Replica timeline diverged from leader, reinitializing"
+                            );
+                            telemetry.send(TelemetryEvent::TimelineDiverged {
+                                node: config.name.clone(),
+                                local_timeline: lag_status.local_timeline.unwrap_or(0),
+                                leader_timeline: lag_status.leader_timeline.unwrap_or(0),
+                            });
+                            if let Err(e) = reinitialize(&health_client, config.api_port).await {
+                                warn!(error = %e, "reinitialize request failed");
+                            }
+                            lag_failures = 0;
+                        } else if lag_status.lag_bytes.unwrap_or(0) > config.max_replication_lag_bytes {
+                            lag_failures += 1;
+                            warn!(
+                                lag_bytes = lag_status.lag_bytes,
+                                max_replication_lag_bytes = config.max_replication_lag_bytes,
+                                "Replication lag exceeds threshold ({}/{})",
+                                lag_failures, config.max_failures
+                            );
+
+                            if lag_failures >= config.max_failures {
+                                telemetry.send(TelemetryEvent::ReplicationLagExceeded {
+                                    node: config.name.clone(),
+                                    lag_bytes: lag_status.lag_bytes.unwrap_or(0),
+                                    threshold_bytes: config.max_replication_lag_bytes,
+                                });
+                                if let Err(e) = reinitialize(&health_client, config.api_port).await {
+                                    warn!(error = %e, "reinitialize request failed");
+                                }
+                                lag_failures = 0;
+                            }
+                        } else {
+                            lag_failures = 0;
+                        }
+                    }
                 } else {
                     failures += 1;
                     warn!("Health check failed ({}/{})", failures, config.max_failures);
@@ -441,6 +998,11 @@ async fn main() -> Result<()> {
                     }
                 }
             }
+            _ = sleep(maintenance_schedule.map(|s| s.next_fire()).unwrap_or(Duration::from_secs(3600))), if maintenance_schedule.is_some() => {
+                if let Err(e) = maintenance::run_maintenance(&health_client, config.api_port, config.maintenance_action).await {
+                    warn!(error = %e, "scheduled maintenance action failed");
+                }
+            }
         }
     }
 }
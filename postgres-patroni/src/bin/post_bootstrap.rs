@@ -5,43 +5,85 @@
 //! We MUST read credentials from /tmp/patroni.yml
 
 use anyhow::{anyhow, Context, Result};
-use common::{init_logging, Telemetry, TelemetryEvent};
-use postgres_patroni::{extract_yaml_value, parse_yaml_value, volume_root};
+use common::{init_logging, Pg, PgCredentials, Telemetry, TelemetryEvent};
+use postgres_patroni::encrypted_secrets::EncryptedSecrets;
+use postgres_patroni::migrations::{self, Credentials};
+use postgres_patroni::{parse_patroni_yaml, volume_root};
 use std::env;
-use std::io::Write;
 use std::path::Path;
-use std::process::{Command, Stdio};
 use std::time::Instant;
-use tracing::{error, info};
-
-const PATRONI_CONFIG: &str = "/tmp/patroni.yml";
-
-struct Credentials {
-    repl_user: String,
-    repl_pass: String,
-    superuser: String,
-    superuser_pass: String,
-    app_user: String,
-    app_pass: String,
-    app_db: String,
+use tracing::{error, info, warn};
+
+const DEFAULT_PATRONI_CONFIG: &str = "/tmp/patroni.yml";
+const DEFAULT_PG_SOCKET_DIR: &str = "/var/run/postgresql";
+
+/// Path to the rendered Patroni config. Overridable via
+/// `PATRONI_CONFIG_PATH` so an integration test harness can point this
+/// binary at a throwaway config instead of the real `/tmp/patroni.yml`.
+fn patroni_config_path() -> String {
+    env::var("PATRONI_CONFIG_PATH").unwrap_or_else(|_| DEFAULT_PATRONI_CONFIG.to_string())
 }
 
-fn read_credentials() -> Result<Credentials> {
-    let content =
-        std::fs::read_to_string(PATRONI_CONFIG).context("Failed to read Patroni config")?;
+/// Directory containing the Unix socket this binary connects to Postgres
+/// through. Overridable via `PATRONI_PG_SOCKET_DIR` for the same reason as
+/// `patroni_config_path`.
+fn pg_socket_dir() -> String {
+    env::var("PATRONI_PG_SOCKET_DIR").unwrap_or_else(|_| DEFAULT_PG_SOCKET_DIR.to_string())
+}
+
+/// Port Postgres is listening on, read from `PGPORT`. Unset in production -
+/// Patroni always runs this on the default port - but lets an integration
+/// test harness point this binary at a throwaway instance on a random port,
+/// the same way `PATRONI_CONFIG_PATH`/`PATRONI_PG_SOCKET_DIR` do.
+fn pg_port() -> Option<u16> {
+    env::var("PGPORT").ok().and_then(|v| v.parse().ok())
+}
+
+/// Read credentials from Consul KV when `CONSUL_HTTP_ADDR` is set, falling
+/// back to the rendered Patroni YAML (the only source before Consul was
+/// available) when Consul isn't configured or the request fails.
+async fn read_credentials() -> Result<Credentials> {
+    let prefix =
+        env::var("CONSUL_CREDENTIALS_PREFIX").unwrap_or_else(|_| "postgres-ha/credentials".to_string());
+
+    match postgres_patroni::consul::read_credentials(&prefix).await {
+        Ok(Some(creds)) => return Ok(creds),
+        Ok(None) => {}
+        Err(e) => warn!(error = %e, "Consul credential read failed, falling back to Patroni config"),
+    }
 
-    let repl_user = extract_nested_value(&content, "authentication", "replication", "username")
+    read_credentials_from_yaml()
+}
+
+fn read_credentials_from_yaml() -> Result<Credentials> {
+    let content =
+        std::fs::read_to_string(patroni_config_path()).context("Failed to read Patroni config")?;
+
+    // `EncryptedSecrets::from_yaml` aborts here with a "wrong passphrase"
+    // error if PATRONI_SECRETS_PASSPHRASE is set but can't decrypt the
+    // config's verify_blob, so nothing below runs against garbage
+    // credentials decrypted under the wrong key.
+    let encryption = EncryptedSecrets::from_yaml(&content)?;
+    let doc = parse_patroni_yaml(&content)?;
+
+    let repl_user = doc
+        .authentication
+        .replication
+        .username
         .ok_or_else(|| anyhow!("Could not extract replication username"))?;
-    let repl_pass = extract_nested_value(&content, "authentication", "replication", "password")
+    let repl_pass = decrypt_secret(&encryption, doc.authentication.replication.password, "authentication.replication.password")?
         .ok_or_else(|| anyhow!("Could not extract replication password"))?;
-    let superuser = extract_nested_value(&content, "authentication", "superuser", "username")
+    let superuser = doc
+        .authentication
+        .superuser
+        .username
         .ok_or_else(|| anyhow!("Could not extract superuser username"))?;
-    let superuser_pass = extract_nested_value(&content, "authentication", "superuser", "password")
+    let superuser_pass = decrypt_secret(&encryption, doc.authentication.superuser.password, "authentication.superuser.password")?
         .ok_or_else(|| anyhow!("Could not extract superuser password"))?;
 
-    let app_user = extract_yaml_value(&content, "app_user", "username").unwrap_or_default();
-    let app_pass = extract_yaml_value(&content, "app_user", "password").unwrap_or_default();
-    let app_db = extract_yaml_value(&content, "app_user", "database").unwrap_or_default();
+    let app_user = doc.app_user.username.unwrap_or_default();
+    let app_pass = decrypt_secret(&encryption, doc.app_user.password, "app_user.password")?.unwrap_or_default();
+    let app_db = doc.app_user.database.unwrap_or_default();
 
     Ok(Credentials {
         repl_user,
@@ -54,127 +96,30 @@ fn read_credentials() -> Result<Credentials> {
     })
 }
 
-fn extract_nested_value(
-    content: &str,
-    section1: &str,
-    section2: &str,
-    key: &str,
-) -> Option<String> {
-    let mut in_section1 = false;
-    let mut in_section2 = false;
-    let mut section1_indent = 0;
-    let mut section2_indent = 0;
-
-    for line in content.lines() {
-        let trimmed = line.trim_start();
-        let indent = line.len() - trimmed.len();
-
-        if trimmed.starts_with(&format!("{}:", section1)) {
-            in_section1 = true;
-            section1_indent = indent;
-            continue;
-        }
-
-        if in_section1 {
-            if !trimmed.is_empty() && indent <= section1_indent && !trimmed.starts_with('#') {
-                in_section1 = false;
-                in_section2 = false;
-                continue;
-            }
-
-            if trimmed.starts_with(&format!("{}:", section2)) {
-                in_section2 = true;
-                section2_indent = indent;
-                continue;
-            }
-
-            if in_section2 {
-                if !trimmed.is_empty() && indent <= section2_indent && !trimmed.starts_with('#') {
-                    in_section2 = false;
-                    continue;
-                }
-
-                if trimmed.starts_with(&format!("{}:", key)) {
-                    return parse_yaml_value(trimmed);
-                }
-            }
-        }
-    }
-
-    None
-}
-
-fn run_psql(superuser: &str, sql: &str) -> Result<String> {
-    let output = Command::new("env")
-        .args(["-i"])
-        .env("PATH", env::var("PATH").unwrap_or_default())
-        .args([
-            "psql",
-            "-v",
-            "ON_ERROR_STOP=1",
-            "-h",
-            "/var/run/postgresql",
-            "-U",
-            superuser,
-            "-d",
-            "postgres",
-            "-c",
-            sql,
-        ])
-        .stdin(Stdio::null())
-        .output()
-        .context("Failed to run psql")?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(anyhow!(
-            "psql failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ))
-    }
-}
-
-fn run_psql_script(superuser: &str, sql: &str) -> Result<String> {
-    let mut child = Command::new("env")
-        .args(["-i"])
-        .env("PATH", env::var("PATH").unwrap_or_default())
-        .args([
-            "psql",
-            "-v",
-            "ON_ERROR_STOP=1",
-            "-h",
-            "/var/run/postgresql",
-            "-U",
-            superuser,
-            "-d",
-            "postgres",
-        ])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .context("Failed to spawn psql")?;
-
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin.write_all(sql.as_bytes())?;
+/// Decrypts `raw` when encryption is enabled, otherwise returns it
+/// unchanged. `field` is only used to label a decrypt failure.
+fn decrypt_secret(encryption: &Option<EncryptedSecrets>, raw: Option<String>, field: &str) -> Result<Option<String>> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    // An empty password field (no app user configured) is never sealed on
+    // the write side (see `maybe_encrypt_passwords` in patroni_runner), so
+    // it must not be run through `open` here either.
+    if raw.is_empty() {
+        return Ok(Some(raw));
     }
-
-    let output = child.wait_with_output()?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(anyhow!(
-            "psql failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ))
+    match encryption {
+        Some(enc) => enc.open(&raw).map(Some).with_context(|| format!("failed to decrypt {field}")),
+        None => Ok(Some(raw)),
     }
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     let _guard = init_logging("post-bootstrap");
 
+    common::merge_dotenv()?;
+
     let start = Instant::now();
     let telemetry = Telemetry::from_env("postgres-ha");
     let node_name = env::var("PATRONI_NAME").unwrap_or_else(|_| "unknown".to_string());
@@ -186,8 +131,11 @@ fn main() -> Result<()> {
         is_fresh: true,
     });
 
-    if !Path::new(PATRONI_CONFIG).exists() {
-        error!(path = PATRONI_CONFIG, "Patroni config not found");
+    // Consul doesn't need the rendered YAML at all - only bail out early on a
+    // missing file when we'd actually have to fall back to reading it.
+    let config_path = patroni_config_path();
+    if env::var("CONSUL_HTTP_ADDR").is_err() && !Path::new(&config_path).exists() {
+        error!(path = %config_path, "Patroni config not found");
         telemetry.send(TelemetryEvent::BootstrapFailed {
             node: node_name,
             error: "Patroni config not found".to_string(),
@@ -196,7 +144,7 @@ fn main() -> Result<()> {
         std::process::exit(1);
     }
 
-    let creds = match read_credentials() {
+    let creds = match read_credentials().await {
         Ok(c) => c,
         Err(e) => {
             error!(error = %e, "Failed to read credentials");
@@ -221,124 +169,52 @@ fn main() -> Result<()> {
 
     info!(superuser = %creds.superuser, "Setting up users");
 
-    let sql = format!(
-        r#"
-SET password_encryption = 'scram-sha-256';
-
-DO $$
-BEGIN
-    EXECUTE format('ALTER ROLE %I WITH PASSWORD %L', '{superuser}', '{superuser_pass}');
-    RAISE NOTICE 'Set password for superuser: {superuser}';
-END
-$$;
-
-DO $$
-BEGIN
-    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = '{repl_user}') THEN
-        EXECUTE format('CREATE ROLE %I WITH REPLICATION LOGIN PASSWORD %L', '{repl_user}', '{repl_pass}');
-        RAISE NOTICE 'Created replication user: {repl_user}';
-    ELSE
-        EXECUTE format('ALTER ROLE %I WITH REPLICATION LOGIN PASSWORD %L', '{repl_user}', '{repl_pass}');
-        RAISE NOTICE 'Updated replication user: {repl_user}';
-    END IF;
-END
-$$;
-
-DO $$
-BEGIN
-    IF '{app_user}' = '{superuser}' THEN
-        RAISE NOTICE 'App user same as superuser, skipping';
-    ELSIF '{app_user}' = '' OR '{app_pass}' = '' THEN
-        RAISE NOTICE 'App user not configured, skipping';
-    ELSIF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = '{app_user}') THEN
-        EXECUTE format('CREATE ROLE %I WITH LOGIN PASSWORD %L', '{app_user}', '{app_pass}');
-        RAISE NOTICE 'Created app user: {app_user}';
-    ELSE
-        EXECUTE format('ALTER ROLE %I WITH PASSWORD %L', '{app_user}', '{app_pass}');
-        RAISE NOTICE 'Updated app user: {app_user}';
-    END IF;
-END
-$$;
-
-DO $$
-BEGIN
-    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'postgres') THEN
-        EXECUTE format('CREATE ROLE postgres WITH SUPERUSER LOGIN PASSWORD %L', '{superuser_pass}');
-        RAISE NOTICE 'Created postgres superuser for compatibility';
-    ELSE
-        ALTER ROLE postgres WITH SUPERUSER;
-        RAISE NOTICE 'Ensured postgres has superuser privileges';
-    END IF;
-END
-$$;
-"#,
-        superuser = creds.superuser,
-        superuser_pass = creds.superuser_pass,
-        repl_user = creds.repl_user,
-        repl_pass = creds.repl_pass,
-        app_user = creds.app_user,
-        app_pass = creds.app_pass,
-    );
-
-    if let Err(e) = run_psql_script(&creds.superuser, &sql) {
-        error!(error = %e, "Failed to create users");
-        telemetry.send(TelemetryEvent::BootstrapFailed {
-            node: node_name,
-            error: e.to_string(),
-            phase: "create_users".to_string(),
-        });
-        std::process::exit(1);
-    }
-
-    // Create app database if configured
-    if !creds.app_db.is_empty() && creds.app_db != "postgres" {
-        info!(database = %creds.app_db, "Checking app database");
-
-        let db_exists = run_psql(
-            &creds.superuser,
-            &format!(
-                "SELECT 1 FROM pg_database WHERE datname = '{}'",
-                creds.app_db
-            ),
-        )?;
-
-        if !db_exists.contains('1') {
-            info!(database = %creds.app_db, "Creating app database");
-            run_psql(
-                &creds.superuser,
-                &format!("CREATE DATABASE \"{}\"", creds.app_db),
-            )?;
+    let pg = match Pg::new(PgCredentials {
+        user: creds.superuser.clone(),
+        password: creds.superuser_pass.clone(),
+        dbname: "postgres".to_string(),
+        socket_dir: pg_socket_dir(),
+        port: pg_port(),
+    }) {
+        Ok(pg) => pg,
+        Err(e) => {
+            error!(error = %e, "Failed to connect to Postgres");
+            telemetry.send(TelemetryEvent::BootstrapFailed {
+                node: node_name,
+                error: e.to_string(),
+                phase: "connect".to_string(),
+            });
+            std::process::exit(1);
         }
+    };
 
-        if !creds.app_user.is_empty() && creds.app_user != creds.superuser {
-            let grant_sql = format!(
-                r#"
-DO $$
-BEGIN
-    EXECUTE format('GRANT ALL PRIVILEGES ON DATABASE %I TO %I', '{db}', '{user}');
-END
-$$;
-"#,
-                db = creds.app_db,
-                user = creds.app_user,
-            );
-            run_psql_script(&creds.superuser, &grant_sql)?;
+    let applied = match migrations::run_migrations(&pg, &creds).await {
+        Ok(applied) => applied,
+        Err(e) => {
+            error!(error = %e, phase = %e.phase, "Migration run failed");
+            telemetry.send(TelemetryEvent::BootstrapFailed {
+                node: node_name,
+                error: e.source.to_string(),
+                phase: e.phase.clone(),
+            });
+            std::process::exit(1);
         }
-    }
-
-    let mut users_created = vec![creds.superuser.clone(), creds.repl_user.clone()];
-    if !creds.app_user.is_empty() && creds.app_user != creds.superuser {
-        users_created.push(creds.app_user.clone());
-    }
+    };
 
     info!(
         superuser = %creds.superuser,
         replication = %creds.repl_user,
         app_user = %creds.app_user,
         database = %creds.app_db,
-        "Users created"
+        applied = ?applied,
+        "Migrations applied"
     );
 
+    let mut users_created = vec![creds.superuser.clone(), creds.repl_user.clone()];
+    if !creds.app_user.is_empty() && creds.app_user != creds.superuser {
+        users_created.push(creds.app_user.clone());
+    }
+
     // Mark bootstrap complete
     let marker_path = format!("{}/.patroni_bootstrap_complete", volume_root());
     std::fs::write(&marker_path, "").context("Failed to write bootstrap marker")?;
@@ -6,8 +6,9 @@
 
 use anyhow::{anyhow, Context, Result};
 use postgres_patroni::{
-    cert_expires_within, is_patroni_enabled, is_railway, is_valid_x509v3_cert, pgdata, ssl_dir,
-    sudo_command, EXPECTED_VOLUME_MOUNT_PATH,
+    cert_covers_expected_hostnames, cert_expires_within, is_patroni_enabled, is_railway,
+    is_valid_x509v3_cert, pgdata, ssl_dir, sudo_command, warm_ca_cert_cache,
+    EXPECTED_VOLUME_MOUNT_PATH,
 };
 use std::env;
 use std::os::unix::process::CommandExt;
@@ -57,6 +58,19 @@ async fn check_and_generate_ssl() -> Result<()> {
         return Ok(());
     }
 
+    // Regenerate if the cert is valid but doesn't cover this node's hostname
+    let covers_hostname = timeout(Duration::from_secs(30), async {
+        cert_covers_expected_hostnames(&server_crt).await
+    })
+    .await
+    .unwrap_or(false);
+
+    if !covers_hostname {
+        info!("Certificate does not cover this node's hostname, regenerating certificates...");
+        run_init_ssl().await?;
+        return Ok(());
+    }
+
     // Regenerate if the certificate has expired or will expire (30 days)
     let expires_soon = timeout(Duration::from_secs(30), async {
         cert_expires_within(&server_crt, 2592000).await // 30 days in seconds
@@ -83,6 +97,13 @@ async fn main() -> Result<()> {
         .with_target(false)
         .init();
 
+    let root_crt = format!("{}/root.crt", ssl_dir());
+    if Path::new(&root_crt).exists() {
+        if let Err(e) = warm_ca_cert_cache(&root_crt).await {
+            error!(error = %e, "Failed to warm CA certificate cache");
+        }
+    }
+
     let pgdata = pgdata();
     let data_dir = EXPECTED_VOLUME_MOUNT_PATH;
 
@@ -183,6 +204,12 @@ async fn main() -> Result<()> {
             run_init_ssl().await?;
         }
 
+        // Regenerate if the cert is valid but doesn't cover this node's hostname
+        if Path::new(&server_crt).exists() && !cert_covers_expected_hostnames(&server_crt).await {
+            info!("Certificate does not cover this node's hostname, regenerating certificates...");
+            run_init_ssl().await?;
+        }
+
         // Regenerate if the certificate has expired or will expire (30 days)
         if Path::new(&server_crt).exists() && cert_expires_within(&server_crt, 2592000).await {
             info!("Certificate has or will expire soon, regenerating certificates...");
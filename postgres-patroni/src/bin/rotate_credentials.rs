@@ -0,0 +1,206 @@
+//! Credential rotation command
+//!
+//! Rolls the superuser, replication, and app-role passwords without a full
+//! re-bootstrap: generates new random passwords, applies them via `ALTER
+//! ROLE` on the primary (through `common::rotation`), then rewrites the
+//! corresponding fields - and a `{role}_rotated_at` timestamp under a new
+//! `rotation:` section - in `/tmp/patroni.yml` in the same pass, so the
+//! replication role's new password and Patroni's own replication
+//! connection config never disagree. Run on a schedule against the
+//! primary; `--max-age <seconds>` (or `ROTATION_MAX_AGE_SECS`) skips a
+//! role rotated more recently than that.
+
+use anyhow::{anyhow, Context, Result};
+use common::{alter_role_password, generate_password, init_logging, merge_dotenv, Pg, PgCredentials, Telemetry, TelemetryEvent};
+use postgres_patroni::encrypted_secrets::EncryptedSecrets;
+use postgres_patroni::secrets;
+use postgres_patroni::{extract_nested_value, extract_yaml_value, replace_nested_yaml_value, replace_yaml_value};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+const PATRONI_CONFIG: &str = "/tmp/patroni.yml";
+const PG_SOCKET_DIR: &str = "/var/run/postgresql";
+const DEFAULT_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+fn max_age_secs() -> u64 {
+    let args: Vec<String> = env::args().collect();
+    args.windows(2)
+        .find(|w| w[0] == "--max-age")
+        .and_then(|w| w[1].parse().ok())
+        .or_else(|| env::var("ROTATION_MAX_AGE_SECS").ok().and_then(|v| v.parse().ok()))
+        .unwrap_or(DEFAULT_MAX_AGE_SECS)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// A role is due when it's never been rotated, or its last rotation is
+/// older than `max_age`.
+fn is_due(content: &str, role: &str, max_age: u64) -> bool {
+    extract_yaml_value(content, "rotation", &format!("{role}_rotated_at"))
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(|last| now_secs().saturating_sub(last) >= max_age)
+        .unwrap_or(true)
+}
+
+/// One role's rotation: DB role name, the YAML location of its password
+/// field, the name used in the `rotation:` timestamp section, and the
+/// `secrets::resolve` source (`*_FILE` env var / etcd key) that must be
+/// updated so the next `patroni_runner` startup doesn't regenerate
+/// `patroni.yml` from the pre-rotation password.
+struct RoleSlot<'a> {
+    rotation_key: &'a str,
+    db_role: String,
+    write_password: Box<dyn Fn(&str, &str) -> Option<String> + 'a>,
+    secret_file_env: &'a str,
+    secret_etcd_key: &'a str,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _guard = init_logging("rotate-credentials");
+    merge_dotenv()?;
+
+    let telemetry = Telemetry::from_env("postgres-ha");
+    let node_name = env::var("PATRONI_NAME").unwrap_or_else(|_| "unknown".to_string());
+    let max_age = max_age_secs();
+
+    let content = std::fs::read_to_string(PATRONI_CONFIG).context("Failed to read Patroni config")?;
+    let encryption = EncryptedSecrets::from_yaml(&content)?;
+
+    let superuser = extract_nested_value(&content, "authentication", "superuser", "username")
+        .ok_or_else(|| anyhow!("Could not extract superuser username"))?;
+    let superuser_pass_raw = extract_nested_value(&content, "authentication", "superuser", "password")
+        .ok_or_else(|| anyhow!("Could not extract superuser password"))?;
+    let superuser_pass = match &encryption {
+        Some(enc) => enc.open(&superuser_pass_raw).context("failed to decrypt superuser password")?,
+        None => superuser_pass_raw,
+    };
+    let repl_user = extract_nested_value(&content, "authentication", "replication", "username")
+        .ok_or_else(|| anyhow!("Could not extract replication username"))?;
+    let app_user = extract_yaml_value(&content, "app_user", "username").unwrap_or_default();
+
+    let etcd_hosts = env::var("PATRONI_ETCD3_HOSTS").ok();
+
+    let slots: Vec<RoleSlot> = vec![
+        RoleSlot {
+            rotation_key: "superuser",
+            db_role: superuser.clone(),
+            write_password: Box::new(|c, v| replace_nested_yaml_value(c, "authentication", "superuser", "password", v)),
+            secret_file_env: "PATRONI_SUPERUSER_PASSWORD_FILE",
+            secret_etcd_key: "secrets/patroni/superuser_password",
+        },
+        RoleSlot {
+            rotation_key: "replication",
+            db_role: repl_user.clone(),
+            write_password: Box::new(|c, v| replace_nested_yaml_value(c, "authentication", "replication", "password", v)),
+            secret_file_env: "PATRONI_REPLICATION_PASSWORD_FILE",
+            secret_etcd_key: "secrets/patroni/replication_password",
+        },
+        RoleSlot {
+            rotation_key: "app",
+            db_role: app_user.clone(),
+            write_password: Box::new(|c, v| replace_yaml_value(c, "app_user", "password", v)),
+            secret_file_env: "POSTGRES_PASSWORD_FILE",
+            secret_etcd_key: "secrets/patroni/app_password",
+        },
+    ];
+
+    let due: Vec<&RoleSlot> = slots
+        .iter()
+        .filter(|slot| !slot.db_role.is_empty())
+        .filter(|slot| is_due(&content, slot.rotation_key, max_age))
+        .collect();
+
+    if due.is_empty() {
+        info!(max_age_secs = max_age, "no credentials due for rotation");
+        return Ok(());
+    }
+
+    let pg = Pg::new(PgCredentials {
+        user: superuser,
+        password: superuser_pass,
+        dbname: "postgres".to_string(),
+        socket_dir: PG_SOCKET_DIR.to_string(),
+        port: None,
+    })
+    .context("Failed to connect to Postgres")?;
+
+    let mut rotated_content = content.clone();
+    let mut rotated_roles = Vec::new();
+
+    for slot in due {
+        let new_password = generate_password();
+
+        if let Err(e) = alter_role_password(&pg, &slot.db_role, &new_password).await {
+            error!(role = %slot.db_role, error = %e, "password rotation failed");
+            telemetry.send(TelemetryEvent::RotationFailed {
+                roles: rotated_roles.clone(),
+                node: node_name.clone(),
+                error: e.to_string(),
+            });
+            return Err(e);
+        }
+
+        // The Postgres role's password is now `new_password` - write it back
+        // through the same source `patroni_runner` resolves from, or the
+        // next restart regenerates `patroni.yml` from the stale value and
+        // locks the node out of its own superuser/replication connection.
+        match secrets::persist(slot.secret_file_env, slot.secret_etcd_key, etcd_hosts.as_deref(), &new_password).await {
+            Ok(true) => {}
+            Ok(false) => warn!(
+                role = %slot.db_role,
+                "no {}/PATRONI_ETCD3_HOSTS secret source configured; patroni_runner reads this \
+                 password from a literal env var, which this command cannot update, so the next \
+                 restart will regenerate patroni.yml from the pre-rotation password",
+                slot.secret_file_env,
+            ),
+            Err(e) => {
+                error!(role = %slot.db_role, error = %e, "failed to persist rotated secret to its source of truth");
+                telemetry.send(TelemetryEvent::RotationFailed {
+                    roles: rotated_roles.clone(),
+                    node: node_name.clone(),
+                    error: e.to_string(),
+                });
+                return Err(e);
+            }
+        }
+
+        let stored = match &encryption {
+            Some(enc) => enc.seal(&new_password),
+            None => new_password,
+        };
+
+        rotated_content = (slot.write_password)(&rotated_content, &stored)
+            .ok_or_else(|| anyhow!("could not locate password field for role {}", slot.db_role))?;
+
+        let rotated_at_key = format!("{}_rotated_at", slot.rotation_key);
+        rotated_content = replace_yaml_value(&rotated_content, "rotation", &rotated_at_key, &now_secs().to_string())
+            .unwrap_or_else(|| {
+                warn!(role = %slot.db_role, "no existing 'rotation:' section, appending one");
+                format!(
+                    "{}\nrotation:\n  {}: \"{}\"",
+                    rotated_content,
+                    rotated_at_key,
+                    now_secs()
+                )
+            });
+
+        rotated_roles.push(slot.db_role.clone());
+        info!(role = %slot.db_role, "rotated password");
+    }
+
+    std::fs::write(PATRONI_CONFIG, &rotated_content).context("Failed to write rotated Patroni config")?;
+
+    telemetry.send(TelemetryEvent::CredentialsRotated {
+        roles: rotated_roles,
+        node: node_name,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Template-driven Patroni config generation with user-supplied overrides
+//!
+//! The base `patroni.yml` is rendered from a Handlebars template against the
+//! runner's `Config`, then a user-provided overrides document is deep-merged
+//! into the structured YAML (not the raw text) so operators can tune
+//! `postgresql.parameters`, `bootstrap.dcs.postgresql.parameters`, and
+//! `pg_hba` without editing this crate.
+
+use anyhow::{anyhow, Context, Result};
+use handlebars::Handlebars;
+use serde_yaml::Value;
+use std::collections::HashSet;
+
+const BASE_TEMPLATE: &str = r#"scope: {{scope}}
+name: {{name}}
+
+restapi:
+  listen: 0.0.0.0:{{api_port}}
+  connect_address: {{connect_address}}:{{api_port}}
+
+{{#if is_raft}}
+raft:
+  self_addr: {{raft_self_addr}}
+  partner_addrs: {{raft_partner_addrs}}
+{{#if raft_data_dir}}
+  data_dir: {{raft_data_dir}}
+{{/if}}
+{{else}}
+etcd3:
+  hosts: {{etcd_hosts}}
+{{/if}}
+
+bootstrap:
+  dcs:
+    ttl: {{ttl}}
+    loop_wait: {{loop_wait}}
+    retry_timeout: {{retry_timeout}}
+    maximum_lag_on_failover: 1048576
+    failsafe_mode: true
+    synchronous_mode: {{synchronous_mode}}
+    synchronous_mode_strict: {{synchronous_mode_strict}}
+    synchronous_node_count: {{synchronous_node_count}}
+    postgresql:
+      use_pg_rewind: true
+      use_slots: true
+      parameters:
+        wal_level: replica
+        hot_standby: "on"
+        max_wal_senders: 10
+        max_replication_slots: 10
+        max_connections: 200
+        password_encryption: scram-sha-256
+
+  initdb:
+    - encoding: UTF8
+    - data-checksums
+    - username: {{superuser}}
+
+  pg_hba:
+    - local all all trust
+    - hostssl replication {{repl_user}} 0.0.0.0/0 scram-sha-256
+    - hostssl replication {{repl_user}} ::/0 scram-sha-256
+    - hostssl all all 0.0.0.0/0 scram-sha-256
+    - hostssl all all ::/0 scram-sha-256
+    - host replication {{repl_user}} 0.0.0.0/0 scram-sha-256
+    - host replication {{repl_user}} ::/0 scram-sha-256
+    - host all all 0.0.0.0/0 scram-sha-256
+    - host all all ::/0 scram-sha-256
+
+  post_bootstrap: /post_bootstrap.sh
+
+postgresql:
+  listen: "*:5432"
+  connect_address: {{connect_address}}:5432
+  data_dir: {{data_dir}}
+  pgpass: /tmp/pgpass
+  callbacks:
+    on_role_change: /on_role_change.sh
+    on_start: /on_role_change.sh
+    on_stop: /on_role_change.sh
+  remove_data_directory_on_rewind_failure: true
+  remove_data_directory_on_diverged_timelines: true
+  create_replica_methods:
+{{#if backup_enabled}}
+    - pgbackrest
+{{/if}}
+    - basebackup
+{{#if backup_enabled}}
+  pgbackrest:
+    command: "pgbackrest --delta restore"
+    keep_data: true
+    no_params: true
+{{/if}}
+  basebackup:
+    checkpoint: "fast"
+    wal-method: "stream"
+  authentication:
+    replication:
+      username: "{{repl_user}}"
+      password: "{{repl_pass}}"
+    superuser:
+      username: "{{superuser}}"
+      password: "{{superuser_pass}}"
+  app_user:
+    username: "{{app_user}}"
+    password: "{{app_pass}}"
+    database: "{{app_db}}"
+  parameters:
+    unix_socket_directories: /var/run/postgresql
+    ssl: "on"
+    ssl_cert_file: "{{certs_dir}}/server.crt"
+    ssl_key_file: "{{certs_dir}}/server.key"
+    ssl_ca_file: "{{certs_dir}}/root.crt"
+"#;
+
+/// Keys that must survive the override merge; used as a sanity check that a
+/// user override didn't accidentally clobber something load-bearing.
+const REQUIRED_KEYS: &[&[&str]] = &[
+    &["scope"],
+    &["name"],
+    &["postgresql", "parameters", "ssl_cert_file"],
+    &["postgresql", "parameters", "ssl_key_file"],
+    &["postgresql", "parameters", "ssl_ca_file"],
+];
+
+/// User-supplied overrides, deep-merged into the rendered base document.
+#[derive(Default)]
+pub struct ConfigOverrides {
+    /// Extra/overridden `bootstrap.dcs.postgresql.parameters` entries.
+    pub dcs_postgresql_parameters: Value,
+    /// Extra/overridden `postgresql.parameters` entries.
+    pub postgresql_parameters: Value,
+    /// Extra `pg_hba` lines, appended after the built-in ones.
+    pub extra_pg_hba: Vec<String>,
+    /// Override for `postgresql.create_replica_methods`.
+    pub create_replica_methods: Option<Vec<String>>,
+    /// `encryption:` section to add when secrets-at-rest is enabled -
+    /// `kdf`/`salt`/`verify_blob`, built by `EncryptedSecrets::bootstrap`.
+    pub encryption: Option<Value>,
+}
+
+/// Render the base template against `ctx` (any serializable struct with the
+/// template's field names) and merge `overrides` into the result.
+pub fn render(ctx: &impl serde::Serialize, overrides: &ConfigOverrides) -> Result<String> {
+    let mut hb = Handlebars::new();
+    hb.set_strict_mode(true);
+    // This template renders YAML, not HTML - Handlebars' default HTML
+    // escaping would corrupt any credential substitution containing
+    // `& < > " ' \` =`, which `secrets.rs::resolve()` doesn't rule out for
+    // file/etcd-sourced passwords.
+    hb.register_escape_fn(handlebars::no_escape);
+    hb.register_template_string("patroni", BASE_TEMPLATE)
+        .context("Failed to register patroni.yml template")?;
+
+    let rendered = hb
+        .render("patroni", ctx)
+        .context("Failed to render patroni.yml template")?;
+
+    let mut doc: Value = serde_yaml::from_str(&rendered).context("Failed to parse rendered patroni.yml")?;
+
+    merge_path(
+        &mut doc,
+        &["bootstrap", "dcs", "postgresql", "parameters"],
+        &overrides.dcs_postgresql_parameters,
+    );
+    merge_path(
+        &mut doc,
+        &["postgresql", "parameters"],
+        &overrides.postgresql_parameters,
+    );
+
+    if !overrides.extra_pg_hba.is_empty() {
+        append_pg_hba(&mut doc, &overrides.extra_pg_hba);
+    }
+
+    if let Some(methods) = &overrides.create_replica_methods {
+        set_path(
+            &mut doc,
+            &["postgresql", "create_replica_methods"],
+            Value::Sequence(methods.iter().map(|m| Value::String(m.clone())).collect()),
+        );
+    }
+
+    if let Some(encryption) = &overrides.encryption {
+        set_path(&mut doc, &["encryption"], encryption.clone());
+    }
+
+    validate_required_keys(&doc)?;
+
+    serde_yaml::to_string(&doc).context("Failed to re-serialize merged patroni.yml")
+}
+
+/// Deep-merge a mapping `patch` into the mapping found at `path`, leaving
+/// existing keys not present in `patch` untouched.
+fn merge_path(doc: &mut Value, path: &[&str], patch: &Value) {
+    if patch.is_null() {
+        return;
+    }
+    if let Some(target) = navigate_mut(doc, path) {
+        deep_merge(target, patch);
+    }
+}
+
+fn set_path(doc: &mut Value, path: &[&str], value: Value) {
+    if let Some((last, parents)) = path.split_last() {
+        if let Some(parent) = navigate_mut(doc, parents) {
+            if let Value::Mapping(map) = parent {
+                map.insert(Value::String((*last).to_string()), value);
+            }
+        }
+    }
+}
+
+fn navigate_mut<'a>(doc: &'a mut Value, path: &[&str]) -> Option<&'a mut Value> {
+    let mut current = doc;
+    for segment in path {
+        current = current
+            .as_mapping_mut()?
+            .entry(Value::String((*segment).to_string()))
+            .or_insert(Value::Mapping(Default::default()));
+    }
+    Some(current)
+}
+
+fn deep_merge(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Mapping(target_map), Value::Mapping(patch_map)) => {
+            for (k, v) in patch_map {
+                match target_map.get_mut(k) {
+                    Some(existing) => deep_merge(existing, v),
+                    None => {
+                        target_map.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Append extra `pg_hba` lines, deduping against what's already present
+/// (structurally, via the sequence, rather than substring matching).
+fn append_pg_hba(doc: &mut Value, extra: &[String]) {
+    if let Some(Value::Sequence(seq)) = navigate_mut(doc, &["bootstrap", "pg_hba"]) {
+        let existing: HashSet<String> = seq
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect();
+
+        for line in extra {
+            if !existing.contains(line) {
+                seq.push(Value::String(line.clone()));
+            }
+        }
+    }
+}
+
+fn validate_required_keys(doc: &Value) -> Result<()> {
+    for path in REQUIRED_KEYS {
+        let mut current = doc;
+        for segment in *path {
+            current = current
+                .as_mapping()
+                .and_then(|m| m.get(Value::String((*segment).to_string())))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Merged patroni.yml is missing required key: {}",
+                        path.join(".")
+                    )
+                })?;
+        }
+    }
+    Ok(())
+}
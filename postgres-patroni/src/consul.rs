@@ -0,0 +1,130 @@
+//! Consul KV credential source for post-bootstrap
+//!
+//! Post-bootstrap normally reads superuser/replication/app-user credentials
+//! out of the rendered `patroni.yml` (see `read_credentials` in
+//! `bin/post_bootstrap.rs`), which ties credential rotation to a
+//! container/config redeploy. When `CONSUL_HTTP_ADDR` is set, this instead
+//! fetches the same credentials from a Consul KV prefix (e.g.
+//! `postgres-ha/production/credentials`) over HTTP, so rotating a secret in
+//! Consul takes effect the next time post-bootstrap runs without touching
+//! the image.
+
+use crate::migrations::Credentials;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::debug;
+
+#[derive(Debug, Deserialize)]
+struct KvEntry {
+    #[serde(rename = "Value")]
+    value: Option<String>,
+    #[serde(rename = "ModifyIndex")]
+    modify_index: u64,
+}
+
+/// Per-prefix freshness cache path, so a Consul-backed credential set is
+/// only rewritten/logged as changed when the combined `ModifyIndex` differs
+/// from the last successful read, instead of on every post-bootstrap run.
+fn cache_path(prefix: &str) -> String {
+    format!("/tmp/.consul-credentials-cache-{}", prefix.replace('/', "_"))
+}
+
+async fn get_key(client: &reqwest::Client, addr: &str, key: &str) -> Result<Option<(String, u64)>> {
+    let url = format!("{}/v1/kv/{}", addr.trim_end_matches('/'), key);
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("failed to reach Consul at {}", url))?;
+
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        bail!("Consul KV GET {} returned {}", key, resp.status());
+    }
+
+    let entries: Vec<KvEntry> = resp
+        .json()
+        .await
+        .context("failed to parse Consul KV response")?;
+    let Some(entry) = entries.into_iter().next() else {
+        return Ok(None);
+    };
+    let Some(encoded) = entry.value else {
+        return Ok(None);
+    };
+
+    let decoded = STANDARD
+        .decode(encoded)
+        .context("failed to base64-decode Consul KV value")?;
+    let value = String::from_utf8(decoded).context("Consul KV value was not valid UTF-8")?;
+
+    Ok(Some((value, entry.modify_index)))
+}
+
+/// Fetch credentials from Consul KV under `prefix`. Returns `Ok(None)` (not
+/// an error) when `CONSUL_HTTP_ADDR` isn't set or the prefix has no keys, so
+/// callers can transparently fall back to the YAML file.
+pub async fn read_credentials(prefix: &str) -> Result<Option<Credentials>> {
+    let Ok(addr) = std::env::var("CONSUL_HTTP_ADDR") else {
+        return Ok(None);
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .context("failed to build Consul HTTP client")?;
+
+    let keys = [
+        "superuser/username",
+        "superuser/password",
+        "replication/username",
+        "replication/password",
+        "app_user/username",
+        "app_user/password",
+        "app_user/database",
+    ];
+
+    let mut values = HashMap::new();
+    let mut modify_indices = Vec::new();
+    for key in keys {
+        let full_key = format!("{}/{}", prefix.trim_end_matches('/'), key);
+        if let Some((value, modify_index)) = get_key(&client, &addr, &full_key).await? {
+            values.insert(key, value);
+            modify_indices.push(modify_index);
+        }
+    }
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    let combined_index: u64 = modify_indices.iter().sum();
+    let cache = cache_path(prefix);
+    let changed = std::fs::read_to_string(&cache)
+        .ok()
+        .and_then(|c| c.trim().parse::<u64>().ok())
+        .map(|cached| cached != combined_index)
+        .unwrap_or(true);
+
+    if changed {
+        debug!(prefix, combined_index, "Consul credentials changed since last read");
+        let _ = std::fs::write(&cache, combined_index.to_string());
+    }
+
+    let get = |k: &str| values.get(k).cloned().unwrap_or_default();
+
+    Ok(Some(Credentials {
+        repl_user: get("replication/username"),
+        repl_pass: get("replication/password"),
+        superuser: get("superuser/username"),
+        superuser_pass: get("superuser/password"),
+        app_user: get("app_user/username"),
+        app_pass: get("app_user/password"),
+        app_db: get("app_user/database"),
+    }))
+}
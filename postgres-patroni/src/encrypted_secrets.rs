@@ -0,0 +1,162 @@
+//! Encryption-at-rest for passwords embedded in the rendered `patroni.yml`
+//!
+//! `/tmp/patroni.yml` lands on the same volume as the data directory, so a
+//! superuser/replication password in it is readable by anything that can
+//! read the volume. When `PATRONI_SECRETS_PASSPHRASE` is set, the fields
+//! this module covers are expected to hold ciphertext (produced by
+//! `EncryptedSecrets::seal`) instead of plaintext, and `EncryptedSecrets`
+//! re-derives the key and decrypts them on the way back out. The key is
+//! never stored anywhere - only the salt and a `verify_blob` (a known
+//! plaintext encrypted under the same key) live in the YAML's
+//! `encryption:` section, so a wrong passphrase is caught by a failed
+//! `verify_blob` decryption rather than by garbage credentials reaching
+//! Postgres.
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const VERIFY_PLAINTEXT: &[u8] = b"postgres-ha-secrets-verify-v1";
+
+/// Which KDF produced a given key, recorded alongside the salt in the
+/// `encryption:` section so an older config can't be silently re-derived
+/// with the wrong algorithm after an upgrade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    Argon2id,
+    Pbkdf2Sha256,
+}
+
+impl KdfAlgorithm {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "argon2id" => Ok(Self::Argon2id),
+            "pbkdf2-sha256" => Ok(Self::Pbkdf2Sha256),
+            other => bail!("unknown KDF algorithm '{other}'"),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Argon2id => "argon2id",
+            Self::Pbkdf2Sha256 => "pbkdf2-sha256",
+        }
+    }
+}
+
+fn derive_key(algorithm: KdfAlgorithm, passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    match algorithm {
+        KdfAlgorithm::Argon2id => Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow!("Argon2id key derivation failed: {e}"))?,
+        KdfAlgorithm::Pbkdf2Sha256 => {
+            pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, PBKDF2_ITERATIONS, &mut key)
+        }
+    }
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8]) -> String {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce_bytes), plaintext)
+        .expect("encrypting under a freshly-generated nonce never fails");
+
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    BASE64.encode(sealed)
+}
+
+fn decrypt(key: &[u8; KEY_LEN], encoded: &str) -> Result<Vec<u8>> {
+    let sealed = BASE64
+        .decode(encoded.trim())
+        .context("ciphertext is not valid base64")?;
+    if sealed.len() < NONCE_LEN {
+        bail!("ciphertext is too short to contain a nonce");
+    }
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| anyhow!("decryption failed (wrong passphrase or corrupted ciphertext)"))
+}
+
+/// Holds the key derived from the operator passphrase for the lifetime of
+/// one process. Build via [`EncryptedSecrets::from_yaml`], which also runs
+/// the verify-blob check.
+pub struct EncryptedSecrets {
+    key: [u8; KEY_LEN],
+}
+
+impl EncryptedSecrets {
+    /// Reads the `encryption:` section out of a rendered `patroni.yml` and
+    /// derives the key from `PATRONI_SECRETS_PASSPHRASE`. Returns `Ok(None)`
+    /// when the passphrase env var isn't set, so callers fall back to
+    /// plaintext fields untouched. Fails fast - before any credential is
+    /// read - if the passphrase can't decrypt `verify_blob`.
+    pub fn from_yaml(content: &str) -> Result<Option<Self>> {
+        let passphrase = match std::env::var("PATRONI_SECRETS_PASSPHRASE") {
+            Ok(p) if !p.is_empty() => p,
+            _ => return Ok(None),
+        };
+
+        let algorithm = crate::extract_yaml_value(content, "encryption", "kdf")
+            .map(|name| KdfAlgorithm::parse(&name))
+            .transpose()?
+            .unwrap_or(KdfAlgorithm::Argon2id);
+        let salt_b64 = crate::extract_yaml_value(content, "encryption", "salt").ok_or_else(|| {
+            anyhow!("PATRONI_SECRETS_PASSPHRASE is set but 'encryption.salt' is missing from Patroni config")
+        })?;
+        let verify_blob = crate::extract_yaml_value(content, "encryption", "verify_blob").ok_or_else(|| {
+            anyhow!("PATRONI_SECRETS_PASSPHRASE is set but 'encryption.verify_blob' is missing from Patroni config")
+        })?;
+
+        let salt = BASE64
+            .decode(salt_b64.trim())
+            .context("'encryption.salt' is not valid base64")?;
+        let key = derive_key(algorithm, &passphrase, &salt)?;
+
+        let decrypted = decrypt(&key, &verify_blob).map_err(|_| anyhow!("wrong passphrase"))?;
+        if decrypted != VERIFY_PLAINTEXT {
+            bail!("wrong passphrase");
+        }
+
+        Ok(Some(Self { key }))
+    }
+
+    /// Decrypts a value produced by [`EncryptedSecrets::seal`].
+    pub fn open(&self, value: &str) -> Result<String> {
+        let bytes = decrypt(&self.key, value)?;
+        String::from_utf8(bytes).context("decrypted value is not valid UTF-8")
+    }
+
+    /// Encrypts `plaintext` for storage in the rendered YAML.
+    pub fn seal(&self, plaintext: &str) -> String {
+        encrypt(&self.key, plaintext.as_bytes())
+    }
+
+    /// Generates a fresh salt and `verify_blob` for a newly-enabled
+    /// encrypted config, deriving the key from `passphrase` with
+    /// `algorithm`. Returns the `Self` ready to [`seal`](Self::seal) the
+    /// credentials under the same key, alongside `(kdf, salt_b64,
+    /// verify_blob_b64)` to write into the config's `encryption:` section.
+    pub fn bootstrap(algorithm: KdfAlgorithm, passphrase: &str) -> Result<(Self, &'static str, String, String)> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = derive_key(algorithm, passphrase, &salt)?;
+        let verify_blob = encrypt(&key, VERIFY_PLAINTEXT);
+        Ok((Self { key }, algorithm.as_str(), BASE64.encode(salt), verify_blob))
+    }
+}
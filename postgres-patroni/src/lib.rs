@@ -1,38 +1,149 @@
 //! Shared utilities for postgres-patroni binaries
 
+pub mod config_template;
+pub mod consul;
+pub mod encrypted_secrets;
+pub mod maintenance;
+pub mod metrics;
+pub mod migrations;
+pub mod pgbackrest;
+pub mod secrets;
+pub mod upgrade;
+
 use std::env;
 use std::path::Path;
 use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 use tokio::process::Command;
 use anyhow::{Context, Result};
 
 pub const EXPECTED_VOLUME_MOUNT_PATH: &str = "/var/lib/postgresql/data";
 
+/// One missing/malformed variable. `Config::from_env` collects every
+/// problem it finds into a single `Invalid`, so a misconfigured deployment
+/// sees the whole list at once instead of one error per restart.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("invalid environment configuration:\n{}", .0.join("\n"))]
+    Invalid(Vec<String>),
+}
+
+/// Aggregated environment configuration for postgres-patroni binaries.
+///
+/// Replaces the ad-hoc `env::var` calls that used to be scattered across
+/// `volume_root`, `ssl_dir`, `pgdata`, `is_railway`, and
+/// `is_patroni_enabled`: those functions now just read from the
+/// process-wide `Config` built by [`Config::get`], so every caller still
+/// sees the same values but validation happens once, up front.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub volume_root: String,
+    pub ssl_dir: String,
+    pub pgdata: String,
+    pub is_railway: bool,
+    pub is_patroni_enabled: bool,
+}
+
+impl Config {
+    /// Reads and validates the environment, merging a `.env` file first
+    /// (a no-op if none is present). Returns every malformed/missing
+    /// variable found, not just the first.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let _ = crate::merge_dotenv_if_available();
+
+        let mut errors = Vec::new();
+
+        let volume_root = match env::var("RAILWAY_VOLUME_MOUNT_PATH") {
+            Ok(path) => {
+                if !Path::new(&path).exists() {
+                    errors.push(format!(
+                        "RAILWAY_VOLUME_MOUNT_PATH is set to '{}', but that path does not exist",
+                        path
+                    ));
+                }
+                path
+            }
+            Err(_) => EXPECTED_VOLUME_MOUNT_PATH.to_string(),
+        };
+
+        let ssl_dir = format!("{}/certs", volume_root);
+        let pgdata = env::var("PGDATA").unwrap_or_else(|_| format!("{}/pgdata", volume_root));
+
+        let is_railway = env::var("RAILWAY_ENVIRONMENT").is_ok();
+
+        let is_patroni_enabled = match env::var("PATRONI_ENABLED") {
+            Ok(v) => v.to_lowercase() == "true",
+            Err(_) => false,
+        };
+
+        if is_patroni_enabled {
+            if let Ok(name) = env::var("PATRONI_NAME") {
+                if name.trim().is_empty() {
+                    errors.push("PATRONI_NAME is set but empty".to_string());
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(ConfigError::Invalid(errors));
+        }
+
+        Ok(Self {
+            volume_root,
+            ssl_dir,
+            pgdata,
+            is_railway,
+            is_patroni_enabled,
+        })
+    }
+
+    /// Parses and validates the environment once per process and caches
+    /// the result. Exits the process with the aggregated error on first
+    /// access if the environment is invalid, so a bad config fails at
+    /// startup rather than deep inside whichever helper happens to read
+    /// its value first.
+    pub fn get() -> &'static Config {
+        static CONFIG: OnceLock<Config> = OnceLock::new();
+        CONFIG.get_or_init(|| {
+            Config::from_env().unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            })
+        })
+    }
+}
+
+/// `common::merge_dotenv` without making `postgres-patroni` depend on the
+/// exact return type - binaries that want to surface merge errors still
+/// call `common::merge_dotenv()` themselves before anything else runs.
+fn merge_dotenv_if_available() -> Result<()> {
+    common::merge_dotenv()
+}
+
 /// Get the volume root path from environment or default
 pub fn volume_root() -> String {
-    env::var("RAILWAY_VOLUME_MOUNT_PATH").unwrap_or_else(|_| EXPECTED_VOLUME_MOUNT_PATH.to_string())
+    Config::get().volume_root.clone()
 }
 
 /// Get the SSL directory path
 pub fn ssl_dir() -> String {
-    format!("{}/certs", volume_root())
+    Config::get().ssl_dir.clone()
 }
 
 /// Get the PGDATA path
 pub fn pgdata() -> String {
-    env::var("PGDATA").unwrap_or_else(|_| format!("{}/pgdata", volume_root()))
+    Config::get().pgdata.clone()
 }
 
 /// Check if running on Railway
 pub fn is_railway() -> bool {
-    env::var("RAILWAY_ENVIRONMENT").is_ok()
+    Config::get().is_railway
 }
 
 /// Check if Patroni mode is enabled
 pub fn is_patroni_enabled() -> bool {
-    env::var("PATRONI_ENABLED")
-        .map(|v| v.to_lowercase() == "true")
-        .unwrap_or(false)
+    Config::get().is_patroni_enabled
 }
 
 /// Run a command with sudo
@@ -113,7 +224,165 @@ pub async fn cert_expires_within(cert_path: &str, seconds: u64) -> bool {
     }
 }
 
-/// Parse a simple YAML value from a line like "key: value" or "key: 'value'" or 'key: "value"'
+/// Expected hostnames this node's certificate should cover: the Railway
+/// private domain plus any extra names from `CERT_EXTRA_SAN_HOSTNAMES`
+/// (comma-separated). Empty if neither is set.
+pub fn expected_cert_hostnames() -> Vec<String> {
+    let mut hosts = Vec::new();
+
+    if let Ok(domain) = env::var("RAILWAY_PRIVATE_DOMAIN") {
+        if !domain.is_empty() {
+            hosts.push(domain);
+        }
+    }
+
+    if let Ok(extra) = env::var("CERT_EXTRA_SAN_HOSTNAMES") {
+        hosts.extend(
+            extra
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    hosts
+}
+
+/// Check whether a server certificate's Subject Alternative Names cover
+/// `expected_hostname`, matching exactly or via a `*.` wildcard entry,
+/// case-insensitively. A certificate that's otherwise a valid x509v3 cert
+/// but was issued for the wrong domain is a common silent-failure mode in
+/// HA TLS setups, so this is checked separately from `is_valid_x509v3_cert`.
+pub async fn cert_covers_domain(cert_path: &str, expected_hostname: &str) -> bool {
+    if !Path::new(cert_path).exists() {
+        return false;
+    }
+
+    let result = Command::new("openssl")
+        .args(["x509", "-noout", "-text", "-in", cert_path])
+        .output()
+        .await;
+
+    let text = match result {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+        _ => return false,
+    };
+
+    let expected = expected_hostname.to_lowercase();
+    subject_alt_names(&text)
+        .into_iter()
+        .any(|san| san_matches_hostname(&san, &expected))
+}
+
+/// Like `cert_covers_domain`, but checks against every hostname from
+/// `expected_cert_hostnames`. Returns `true` if none are configured, since
+/// there's nothing to validate against in that case.
+pub async fn cert_covers_expected_hostnames(cert_path: &str) -> bool {
+    let hosts = expected_cert_hostnames();
+    if hosts.is_empty() {
+        return true;
+    }
+
+    for host in &hosts {
+        if cert_covers_domain(cert_path, host).await {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn subject_alt_names(openssl_text_output: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut in_san = false;
+
+    for line in openssl_text_output.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("X509v3 Subject Alternative Name:") {
+            in_san = true;
+            continue;
+        }
+
+        if in_san {
+            for entry in trimmed.split(',') {
+                if let Some(dns) = entry.trim().strip_prefix("DNS:") {
+                    names.push(dns.trim().to_lowercase());
+                }
+            }
+            in_san = false;
+        }
+    }
+
+    names
+}
+
+fn san_matches_hostname(san: &str, hostname_lower: &str) -> bool {
+    if san == hostname_lower {
+        return true;
+    }
+
+    match san.strip_prefix("*.").zip(hostname_lower.split_once('.')) {
+        Some((wildcard_suffix, (_, hostname_suffix))) => wildcard_suffix == hostname_suffix,
+        None => false,
+    }
+}
+
+struct CaCertCache {
+    mtime: SystemTime,
+    text: String,
+}
+
+static CA_CERT_CACHE: OnceLock<Mutex<Option<CaCertCache>>> = OnceLock::new();
+
+/// Pre-parse `root.crt` (via `openssl x509 -text`) and cache the result, so
+/// repeated validation/expiry checks in a monitoring loop don't re-read and
+/// re-parse the CA file on every tick. Safe to call unconditionally on
+/// startup; the cache transparently refreshes itself if the file's mtime
+/// changes, so it never needs to be invalidated by hand.
+pub async fn warm_ca_cert_cache(root_crt_path: &str) -> Result<()> {
+    ca_cert_text(root_crt_path).await.map(|_| ())
+}
+
+async fn ca_cert_text(root_crt_path: &str) -> Result<String> {
+    let mtime = std::fs::metadata(root_crt_path)
+        .and_then(|m| m.modified())
+        .context("Failed to stat CA certificate")?;
+
+    let cache = CA_CERT_CACHE.get_or_init(|| Mutex::new(None));
+    if let Some(entry) = cache.lock().unwrap().as_ref() {
+        if entry.mtime == mtime {
+            return Ok(entry.text.clone());
+        }
+    }
+
+    let output = Command::new("openssl")
+        .args(["x509", "-noout", "-text", "-in", root_crt_path])
+        .output()
+        .await
+        .context("Failed to run openssl on CA certificate")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "openssl failed to parse CA certificate: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    *cache.lock().unwrap() = Some(CaCertCache {
+        mtime,
+        text: text.clone(),
+    });
+
+    Ok(text)
+}
+
+/// Parse a simple YAML scalar from a line like "key: value" or
+/// "key: 'value'" or 'key: "value"'. Kept for any caller that only has one
+/// line of YAML in hand rather than a whole document; `extract_yaml_value`/
+/// `extract_nested_value` no longer use it themselves (see below).
 pub fn parse_yaml_value(line: &str) -> Option<String> {
     let parts: Vec<&str> = line.splitn(2, ':').collect();
     if parts.len() != 2 {
@@ -131,39 +400,162 @@ pub fn parse_yaml_value(line: &str) -> Option<String> {
     Some(value.to_string())
 }
 
-/// Extract a value from a YAML file given a section and key
-/// Simple parser that looks for patterns like:
-///   section:
-///     key: value
+/// Typed shape of the `patroni.yml` sections every binary in this crate
+/// reads. `#[serde(default)]` throughout so a config missing a whole
+/// section (e.g. no `app_user:` configured) deserializes to empty values
+/// instead of failing the parse.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct PatroniYamlDoc {
+    #[serde(default)]
+    pub authentication: PatroniAuthentication,
+    #[serde(default)]
+    pub app_user: PatroniCredentialPair,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct PatroniAuthentication {
+    #[serde(default)]
+    pub replication: PatroniCredentialPair,
+    #[serde(default)]
+    pub superuser: PatroniCredentialPair,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct PatroniCredentialPair {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub database: Option<String>,
+}
+
+/// Parses `content` as a `patroni.yml` document via `serde_yaml`, handling
+/// anchors, flow mappings, multi-line scalars, and quoting correctly -
+/// all things the scanner `extract_yaml_value`/`extract_nested_value` used
+/// to implement by hand got wrong.
+pub fn parse_patroni_yaml(content: &str) -> Result<PatroniYamlDoc> {
+    serde_yaml::from_str(content).context("failed to parse Patroni config as YAML")
+}
+
+/// Looks up an arbitrary `section.key` scalar via a real YAML parse, for
+/// sections (`encryption:`, `rotation:`, ...) that aren't part of the fixed
+/// `PatroniYamlDoc` shape. A thin compatibility shim over `serde_yaml`,
+/// kept so callers that only need one scalar don't have to match on a
+/// typed struct.
 pub fn extract_yaml_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let doc: serde_yaml::Value = serde_yaml::from_str(content).ok()?;
+    yaml_scalar(doc.get(section)?.get(key)?)
+}
+
+/// Nested counterpart of `extract_yaml_value`, for `section1.section2.key`.
+pub fn extract_nested_value(content: &str, section1: &str, section2: &str, key: &str) -> Option<String> {
+    let doc: serde_yaml::Value = serde_yaml::from_str(content).ok()?;
+    yaml_scalar(doc.get(section1)?.get(section2)?.get(key)?)
+}
+
+/// Renders any scalar YAML value as a string the way the old line-based
+/// scanner's callers expect (e.g. a bare `30` under `rotated_at:` came back
+/// as `"30"` from the scanner too).
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Rewrites the value for `key` inside `section:` in a rendered
+/// `patroni.yml`, preserving every other line verbatim and returning the
+/// whole updated file. Returns `None` if the section or key isn't found,
+/// mirroring `extract_yaml_value`.
+pub fn replace_yaml_value(content: &str, section: &str, key: &str, new_value: &str) -> Option<String> {
     let mut in_section = false;
     let mut section_indent = 0;
+    let mut out = Vec::new();
+    let mut replaced = false;
 
     for line in content.lines() {
         let trimmed = line.trim_start();
         let indent = line.len() - trimmed.len();
 
-        // Check if we found the section
         if trimmed.starts_with(&format!("{}:", section)) {
             in_section = true;
             section_indent = indent;
+            out.push(line.to_string());
             continue;
         }
 
-        // If we're in the section and at correct indent level
         if in_section {
-            // If we hit a line at same or less indent (except empty lines), we're out of section
             if !trimmed.is_empty() && indent <= section_indent && !trimmed.starts_with('#') {
                 in_section = false;
+            } else if trimmed.starts_with(&format!("{}:", key)) {
+                out.push(format!("{}{}: {}", " ".repeat(indent), key, yaml_quote(new_value)));
+                replaced = true;
                 continue;
             }
+        }
+
+        out.push(line.to_string());
+    }
+
+    replaced.then(|| out.join("\n"))
+}
+
+/// Nested counterpart of `replace_yaml_value`, matching `extract_nested_value`.
+pub fn replace_nested_yaml_value(
+    content: &str,
+    section1: &str,
+    section2: &str,
+    key: &str,
+    new_value: &str,
+) -> Option<String> {
+    let mut in_section1 = false;
+    let mut in_section2 = false;
+    let mut section1_indent = 0;
+    let mut section2_indent = 0;
+    let mut out = Vec::new();
+    let mut replaced = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
 
-            // Look for our key
-            if trimmed.starts_with(&format!("{}:", key)) {
-                return parse_yaml_value(trimmed);
+        if trimmed.starts_with(&format!("{}:", section1)) {
+            in_section1 = true;
+            section1_indent = indent;
+            out.push(line.to_string());
+            continue;
+        }
+
+        if in_section1 {
+            if !trimmed.is_empty() && indent <= section1_indent && !trimmed.starts_with('#') {
+                in_section1 = false;
+                in_section2 = false;
+            } else if trimmed.starts_with(&format!("{}:", section2)) {
+                in_section2 = true;
+                section2_indent = indent;
+                out.push(line.to_string());
+                continue;
+            } else if in_section2 {
+                if !trimmed.is_empty() && indent <= section2_indent && !trimmed.starts_with('#') {
+                    in_section2 = false;
+                } else if trimmed.starts_with(&format!("{}:", key)) {
+                    out.push(format!("{}{}: {}", " ".repeat(indent), key, yaml_quote(new_value)));
+                    replaced = true;
+                    continue;
+                }
             }
         }
+
+        out.push(line.to_string());
     }
 
-    None
+    replaced.then(|| out.join("\n"))
+}
+
+/// Quotes a scalar for insertion into a rewritten YAML line - double-quoted
+/// style, since it's the one flow scalar form where backslash escapes are
+/// unambiguous regardless of what the value contains (ciphertext, a raw
+/// password, a plain timestamp).
+fn yaml_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
 }
@@ -0,0 +1,171 @@
+//! Scheduled maintenance windows for restarts and other disruptive operations
+//!
+//! Parses a small subset of systemd `OnCalendar` syntax - an optional
+//! weekday filter plus a fixed time, e.g. `Sun *-*-* 03:00:00` - into the
+//! `Duration` until it next fires, so operators can confine a coordinated
+//! restart, a replica reinitialize, or a checkpoint to an off-peak window
+//! instead of the monitoring loop only ever reacting to health failures.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Local, NaiveTime, TimeZone, Weekday};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// A parsed recurring maintenance window.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    weekday: Option<Weekday>,
+    time: NaiveTime,
+}
+
+impl Schedule {
+    /// Parse a systemd-style calendar event of the form
+    /// `<Weekday|*> *-*-* HH:MM:SS` (the weekday may be omitted entirely for
+    /// a daily schedule). Only a weekday filter and a fixed time are
+    /// supported - the date component must be `*-*-*`.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let parts: Vec<&str> = expr.split_whitespace().collect();
+        let (weekday_part, date_part, time_part) = match parts.as_slice() {
+            [weekday, date, time] => (*weekday, *date, *time),
+            [date, time] => ("*", *date, *time),
+            _ => bail!("unrecognized calendar event format: {:?}", expr),
+        };
+
+        if date_part != "*-*-*" {
+            bail!("only '*-*-*' dates are supported, got {:?}", date_part);
+        }
+
+        let weekday = match weekday_part {
+            "*" => None,
+            other => Some(parse_weekday(other)?),
+        };
+
+        let time = NaiveTime::parse_from_str(time_part, "%H:%M:%S")
+            .with_context(|| format!("invalid time {:?} in calendar event", time_part))?;
+
+        Ok(Self { weekday, time })
+    }
+
+    /// Duration from now until this schedule next fires.
+    pub fn next_fire(&self) -> Duration {
+        let now = Local::now();
+        let mut candidate = now.date_naive().and_time(self.time);
+        if candidate <= now.naive_local() {
+            candidate += chrono::Duration::days(1);
+        }
+
+        if let Some(weekday) = self.weekday {
+            while candidate.weekday() != weekday {
+                candidate += chrono::Duration::days(1);
+            }
+        }
+
+        let candidate = Local
+            .from_local_datetime(&candidate)
+            .single()
+            .unwrap_or(now);
+        (candidate - now).to_std().unwrap_or(Duration::from_secs(0))
+    }
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday> {
+    match s {
+        "Mon" => Ok(Weekday::Mon),
+        "Tue" => Ok(Weekday::Tue),
+        "Wed" => Ok(Weekday::Wed),
+        "Thu" => Ok(Weekday::Thu),
+        "Fri" => Ok(Weekday::Fri),
+        "Sat" => Ok(Weekday::Sat),
+        "Sun" => Ok(Weekday::Sun),
+        other => bail!("unrecognized weekday {:?} in calendar event", other),
+    }
+}
+
+/// Which action to perform when a maintenance window fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceAction {
+    Restart,
+    Reinitialize,
+    Checkpoint,
+}
+
+impl MaintenanceAction {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "restart" => Ok(Self::Restart),
+            "reinitialize" => Ok(Self::Reinitialize),
+            "checkpoint" => Ok(Self::Checkpoint),
+            other => bail!("unrecognized maintenance action {:?}", other),
+        }
+    }
+
+    fn api_path(&self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::Reinitialize => "reinitialize",
+            Self::Checkpoint => "checkpoint",
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct ReplicaState {
+    state: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+struct PatroniState {
+    role: Option<String>,
+    #[serde(default)]
+    pending_restart: bool,
+    #[serde(default)]
+    replication: Vec<ReplicaState>,
+}
+
+fn is_leader_role(role: &str) -> bool {
+    matches!(role, "master" | "leader" | "primary" | "standby_leader")
+}
+
+/// Run `action` through the local Patroni REST API when the maintenance
+/// window fires, unless this node is the sole healthy primary - maintenance
+/// must never be the thing that takes the only copy of the data down.
+pub async fn run_maintenance(client: &reqwest::Client, api_port: u16, action: MaintenanceAction) -> Result<()> {
+    let state: PatroniState = client
+        .get(format!("http://localhost:{api_port}/patroni"))
+        .send()
+        .await
+        .context("failed to reach Patroni REST API")?
+        .json()
+        .await
+        .context("failed to parse Patroni REST API response")?;
+
+    let role = state.role.unwrap_or_default();
+    let has_streaming_replica = state
+        .replication
+        .iter()
+        .any(|r| r.state.as_deref() == Some("streaming"));
+
+    if is_leader_role(&role) && !has_streaming_replica {
+        warn!("Skipping scheduled maintenance: sole healthy primary with no streaming replica");
+        return Ok(());
+    }
+
+    if action == MaintenanceAction::Restart && !state.pending_restart {
+        info!("Skipping scheduled restart: nothing pending");
+        return Ok(());
+    }
+
+    let path = action.api_path();
+    info!(action = path, "Running scheduled maintenance action");
+    let resp = client
+        .post(format!("http://localhost:{api_port}/{path}"))
+        .send()
+        .await
+        .with_context(|| format!("maintenance {} request failed", path))?;
+
+    if !resp.status().is_success() {
+        bail!("maintenance {} request returned {}", path, resp.status());
+    }
+
+    Ok(())
+}
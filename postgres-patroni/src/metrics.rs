@@ -0,0 +1,189 @@
+//! Prometheus metrics exporter for the Patroni REST API
+//!
+//! Periodically polls the local Patroni REST API (`/patroni`) and
+//! re-exports cluster state as Prometheus gauges on a dedicated port, so
+//! operators get HA observability without deploying a separate
+//! patroni-exporter sidecar.
+
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Config needed to run the exporter, pulled from the runner's `Config`.
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub scope: String,
+    pub node_name: String,
+    pub api_port: u16,
+    pub scrape_interval: Duration,
+    /// Reused from the runner's health-check loop so both polling paths
+    /// share one connection pool instead of dialing independently.
+    pub client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PatroniStatus {
+    state: Option<String>,
+    role: Option<String>,
+    server_version: Option<u64>,
+    timeline: Option<u64>,
+    pending_restart: Option<bool>,
+    #[serde(default)]
+    replication: Vec<serde_json::Value>,
+}
+
+/// Latest scraped values, rendered to Prometheus exposition format on read.
+#[derive(Default, Clone)]
+struct Snapshot {
+    up: f64,
+    running: f64,
+    role: String,
+    server_version: f64,
+    pending_restart: f64,
+    timeline_number: f64,
+    replication_slots: f64,
+    is_leader: f64,
+}
+
+/// Spawn the scrape loop and the metrics HTTP listener as background tasks.
+/// Returns immediately; a no-op when metrics are disabled.
+pub fn spawn(config: MetricsConfig) {
+    if !config.enabled {
+        return;
+    }
+
+    let snapshot = Arc::new(RwLock::new(Snapshot::default()));
+    let port = config.port;
+    let scope = config.scope.clone();
+    let node_name = config.node_name.clone();
+
+    tokio::spawn(scrape_loop(config, Arc::clone(&snapshot)));
+    tokio::spawn(serve_loop(port, scope, node_name, snapshot));
+}
+
+async fn scrape_loop(config: MetricsConfig, snapshot: Arc<RwLock<Snapshot>>) {
+    let url = format!("http://localhost:{}/patroni", config.api_port);
+
+    loop {
+        let snap = scrape_once(&config.client, &url).await;
+        *snapshot.write().await = snap;
+        tokio::time::sleep(config.scrape_interval).await;
+    }
+}
+
+async fn scrape_once(client: &reqwest::Client, url: &str) -> Snapshot {
+    let status: PatroniStatus = match client.get(url).send().await {
+        Ok(resp) => match resp.json().await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!(error = %e, "failed to parse Patroni REST API response");
+                return Snapshot::default();
+            }
+        },
+        Err(e) => {
+            debug!(error = %e, "failed to reach Patroni REST API");
+            return Snapshot::default();
+        }
+    };
+
+    let role = status.role.clone().unwrap_or_default();
+    let is_leader = matches!(role.as_str(), "master" | "leader" | "primary" | "standby_leader");
+
+    Snapshot {
+        up: 1.0,
+        running: if status.state.as_deref() == Some("running") { 1.0 } else { 0.0 },
+        role,
+        server_version: status.server_version.unwrap_or(0) as f64,
+        pending_restart: if status.pending_restart.unwrap_or(false) { 1.0 } else { 0.0 },
+        timeline_number: status.timeline.unwrap_or(0) as f64,
+        replication_slots: status.replication.len() as f64,
+        is_leader: if is_leader { 1.0 } else { 0.0 },
+    }
+}
+
+fn render(scope: &str, node: &str, snap: &Snapshot) -> String {
+    let labels = format!("scope=\"{}\",node=\"{}\"", scope, node);
+    let mut out = String::new();
+
+    out.push_str("# HELP patroni_up Whether the last scrape of the Patroni REST API succeeded\n");
+    out.push_str("# TYPE patroni_up gauge\n");
+    out.push_str(&format!("patroni_up{{{labels}}} {}\n", snap.up));
+
+    out.push_str("# HELP patroni_running Whether Patroni reports its local state as \"running\"\n");
+    out.push_str("# TYPE patroni_running gauge\n");
+    out.push_str(&format!(
+        "patroni_running{{{labels},role=\"{}\"}} {}\n",
+        snap.role, snap.running
+    ));
+
+    out.push_str("# HELP patroni_postgres_version Running PostgreSQL server_version as reported by Patroni\n");
+    out.push_str("# TYPE patroni_postgres_version gauge\n");
+    out.push_str(&format!("patroni_postgres_version{{{labels}}} {}\n", snap.server_version));
+
+    out.push_str("# HELP patroni_pending_restart Whether PostgreSQL is pending a restart to apply changed parameters\n");
+    out.push_str("# TYPE patroni_pending_restart gauge\n");
+    out.push_str(&format!("patroni_pending_restart{{{labels}}} {}\n", snap.pending_restart));
+
+    out.push_str("# HELP patroni_timeline_number Current PostgreSQL timeline\n");
+    out.push_str("# TYPE patroni_timeline_number gauge\n");
+    out.push_str(&format!("patroni_timeline_number{{{labels}}} {}\n", snap.timeline_number));
+
+    out.push_str("# HELP patroni_replication_slots Number of active replication connections reported by Patroni\n");
+    out.push_str("# TYPE patroni_replication_slots gauge\n");
+    out.push_str(&format!("patroni_replication_slots{{{labels}}} {}\n", snap.replication_slots));
+
+    out.push_str("# HELP patroni_is_leader Whether this node is the current cluster leader\n");
+    out.push_str("# TYPE patroni_is_leader gauge\n");
+    out.push_str(&format!("patroni_is_leader{{{labels}}} {}\n", snap.is_leader));
+
+    out
+}
+
+/// Minimal HTTP/1.1 listener: any request gets the current metrics snapshot
+/// back as `text/plain`. Good enough for a Prometheus scrape target without
+/// pulling in a full HTTP server dependency.
+async fn serve_loop(port: u16, scope: String, node_name: String, snapshot: Arc<RwLock<Snapshot>>) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(l) => l,
+        Err(e) => {
+            warn!(port, error = %e, "failed to bind metrics listener");
+            return;
+        }
+    };
+
+    info!(port, "metrics exporter listening");
+
+    loop {
+        let (mut stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "failed to accept metrics connection");
+                continue;
+            }
+        };
+
+        let scope = scope.clone();
+        let node_name = node_name.clone();
+        let snapshot = Arc::clone(&snapshot);
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            // Drain (and discard) the request; we don't need to route on
+            // path/method since this listener only ever serves metrics.
+            let _ = stream.read(&mut buf).await;
+
+            let body = render(&scope, &node_name, &*snapshot.read().await);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
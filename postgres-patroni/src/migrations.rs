@@ -0,0 +1,313 @@
+//! Versioned, idempotent post-bootstrap setup steps
+//!
+//! Replaces the opaque `/usr/local/bin/post-bootstrap` shell callback with
+//! ordered setup steps (create roles, grant privileges, create the app
+//! database) run through the pooled Postgres client. Each step is recorded
+//! in a `schema_migrations` table keyed by version, so re-running
+//! post-bootstrap after a node is re-bootstrapped only applies steps that
+//! haven't landed yet. The whole run is guarded by a session-level advisory
+//! lock so only one primary applies steps at a time.
+
+use common::{quote_ident, quote_literal, Pg};
+
+/// Arbitrary fixed key for the advisory lock guarding migration application.
+/// Only needs to be unique within this database; any node racing to apply
+/// migrations blocks on the same key.
+const MIGRATION_LOCK_KEY: i64 = 0x706f7374_6267; // "postbg"
+
+/// Error from a migration run, carrying the step name so the caller can
+/// report it as telemetry `phase` without parsing the error message.
+#[derive(Debug, thiserror::Error)]
+#[error("{phase}: {source}")]
+pub struct MigrationError {
+    pub phase: String,
+    #[source]
+    pub source: anyhow::Error,
+}
+
+impl MigrationError {
+    fn new(phase: &str, source: anyhow::Error) -> Self {
+        Self {
+            phase: phase.to_string(),
+            source,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, MigrationError>;
+
+/// Credentials needed to render migration SQL. Mirrors the fields the
+/// post-bootstrap binary already reads out of `patroni.yml`.
+pub struct Credentials {
+    pub repl_user: String,
+    pub repl_pass: String,
+    pub superuser: String,
+    pub superuser_pass: String,
+    pub app_user: String,
+    pub app_pass: String,
+    pub app_db: String,
+}
+
+/// A versioned, idempotent setup step.
+///
+/// `up_sql` is the canonical template for the step, checksummed and stored
+/// in `schema_migrations` so the recorded history reflects what the step
+/// was meant to do. The SQL actually executed is built from it with the
+/// current credentials by `apply`.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub up_sql: &'static str,
+}
+
+impl Migration {
+    /// Cheap, dependency-free FNV-1a hash of `up_sql`, stored alongside the
+    /// applied version so drift in a step's template is visible in the
+    /// migrations table even though it doesn't change the runner's behavior.
+    fn checksum(&self) -> i64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in self.up_sql.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash as i64
+    }
+
+    /// Apply this step against `client`, using `creds` to fill in the
+    /// templated values. Role/database names and passwords are quoted with
+    /// [`quote_ident`]/[`quote_literal`] before going into the SQL text -
+    /// DDL like `ALTER ROLE`/`CREATE DATABASE` can't bind them as query
+    /// parameters, so client-side quoting is what stands in for that here.
+    ///
+    /// Returns `false` when the step was skipped because the credentials it
+    /// needs (`app_user`/`app_pass`/`app_db`) aren't configured yet, so
+    /// `apply_pending` knows not to mark it as applied - otherwise it would
+    /// never retroactively run once an operator adds an app user/database.
+    async fn apply(&self, client: &deadpool_postgres::Client, creds: &Credentials) -> anyhow::Result<bool> {
+        match self.version {
+            1 => {
+                client
+                    .batch_execute(&format!(
+                        "ALTER ROLE {} WITH PASSWORD {}",
+                        quote_ident(&creds.superuser),
+                        quote_literal(&creds.superuser_pass),
+                    ))
+                    .await?;
+            }
+            2 => {
+                let exists = client
+                    .query_opt(
+                        "SELECT 1 FROM pg_roles WHERE rolname = $1",
+                        &[&creds.repl_user],
+                    )
+                    .await?;
+                let verb = if exists.is_some() { "ALTER" } else { "CREATE" };
+                client
+                    .batch_execute(&format!(
+                        "{} ROLE {} WITH REPLICATION LOGIN PASSWORD {}",
+                        verb,
+                        quote_ident(&creds.repl_user),
+                        quote_literal(&creds.repl_pass),
+                    ))
+                    .await?;
+            }
+            3 => {
+                if creds.app_user.is_empty()
+                    || creds.app_pass.is_empty()
+                    || creds.app_user == creds.superuser
+                {
+                    return Ok(false);
+                }
+                let exists = client
+                    .query_opt(
+                        "SELECT 1 FROM pg_roles WHERE rolname = $1",
+                        &[&creds.app_user],
+                    )
+                    .await?;
+                let sql = if exists.is_some() {
+                    format!(
+                        "ALTER ROLE {} WITH PASSWORD {}",
+                        quote_ident(&creds.app_user),
+                        quote_literal(&creds.app_pass),
+                    )
+                } else {
+                    format!(
+                        "CREATE ROLE {} WITH LOGIN PASSWORD {}",
+                        quote_ident(&creds.app_user),
+                        quote_literal(&creds.app_pass),
+                    )
+                };
+                client.batch_execute(&sql).await?;
+            }
+            4 => {
+                let exists = client
+                    .query_opt("SELECT 1 FROM pg_roles WHERE rolname = 'postgres'", &[])
+                    .await?;
+                let sql = if exists.is_some() {
+                    "ALTER ROLE postgres WITH SUPERUSER".to_string()
+                } else {
+                    format!(
+                        "CREATE ROLE postgres WITH SUPERUSER LOGIN PASSWORD {}",
+                        quote_literal(&creds.superuser_pass),
+                    )
+                };
+                client.batch_execute(&sql).await?;
+            }
+            5 => {
+                if creds.app_db.is_empty() || creds.app_db == "postgres" {
+                    return Ok(false);
+                }
+                let exists = client
+                    .query_opt(
+                        "SELECT 1 FROM pg_database WHERE datname = $1",
+                        &[&creds.app_db],
+                    )
+                    .await?;
+                if exists.is_none() {
+                    client
+                        .execute(&format!("CREATE DATABASE {}", quote_ident(&creds.app_db)), &[])
+                        .await?;
+                }
+            }
+            6 => {
+                if creds.app_db.is_empty()
+                    || creds.app_db == "postgres"
+                    || creds.app_user.is_empty()
+                    || creds.app_user == creds.superuser
+                {
+                    return Ok(false);
+                }
+                client
+                    .batch_execute(&format!(
+                        "GRANT ALL PRIVILEGES ON DATABASE {} TO {}",
+                        quote_ident(&creds.app_db),
+                        quote_ident(&creds.app_user),
+                    ))
+                    .await?;
+            }
+            v => unreachable!("no apply rule for migration version {v}"),
+        }
+
+        Ok(true)
+    }
+}
+
+/// Ordered setup steps. Append new steps with the next version number;
+/// never renumber or remove an applied one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "set_superuser_password",
+        up_sql: "ALTER ROLE <superuser> WITH PASSWORD <superuser_pass>",
+    },
+    Migration {
+        version: 2,
+        name: "create_replication_role",
+        up_sql: "CREATE ROLE <repl_user> WITH REPLICATION LOGIN PASSWORD <repl_pass>",
+    },
+    Migration {
+        version: 3,
+        name: "create_app_user",
+        up_sql: "CREATE ROLE <app_user> WITH LOGIN PASSWORD <app_pass>",
+    },
+    Migration {
+        version: 4,
+        name: "ensure_postgres_superuser",
+        up_sql: "ALTER ROLE postgres WITH SUPERUSER",
+    },
+    Migration {
+        version: 5,
+        name: "create_app_database",
+        up_sql: "CREATE DATABASE <app_db>",
+    },
+    Migration {
+        version: 6,
+        name: "grant_app_database",
+        up_sql: "GRANT ALL PRIVILEGES ON DATABASE <app_db> TO <app_user>",
+    },
+];
+
+/// Run all migrations that haven't been applied yet, in version order.
+///
+/// Takes an advisory lock for the duration of the run so only one primary
+/// applies steps at a time, even if post-bootstrap fires on more than one
+/// node. Returns the names of the steps actually applied during this call.
+pub async fn run_migrations(pg: &Pg, creds: &Credentials) -> Result<Vec<String>> {
+    let client = pg
+        .client()
+        .await
+        .map_err(|e| MigrationError::new("connect", e.into()))?;
+
+    client
+        .execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY])
+        .await
+        .map_err(|e| MigrationError::new("migration_lock", e.into()))?;
+
+    let result = apply_pending(&client, creds).await;
+
+    client
+        .execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY])
+        .await
+        .map_err(|e| MigrationError::new("migration_lock", e.into()))?;
+
+    result
+}
+
+async fn apply_pending(
+    client: &deadpool_postgres::Client,
+    creds: &Credentials,
+) -> Result<Vec<String>> {
+    client
+        .batch_execute(
+            r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version bigint PRIMARY KEY,
+    name text NOT NULL,
+    checksum bigint NOT NULL,
+    applied_at timestamptz NOT NULL DEFAULT now()
+)
+"#,
+        )
+        .await
+        .map_err(|e| MigrationError::new("migration_setup", e.into()))?;
+
+    let applied_rows = client
+        .query("SELECT version FROM schema_migrations", &[])
+        .await
+        .map_err(|e| MigrationError::new("migration_setup", e.into()))?;
+    let applied: std::collections::HashSet<i64> =
+        applied_rows.iter().map(|row| row.get::<_, i64>(0)).collect();
+
+    let mut newly_applied = Vec::new();
+
+    for migration in MIGRATIONS {
+        if applied.contains(&(migration.version as i64)) {
+            continue;
+        }
+
+        let applied_this_run = migration
+            .apply(client, creds)
+            .await
+            .map_err(|e| MigrationError::new(migration.name, e))?;
+
+        if !applied_this_run {
+            continue;
+        }
+
+        client
+            .execute(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+                &[
+                    &(migration.version as i64),
+                    &migration.name,
+                    &migration.checksum(),
+                ],
+            )
+            .await
+            .map_err(|e| MigrationError::new(migration.name, e.into()))?;
+
+        newly_applied.push(migration.name.to_string());
+    }
+
+    Ok(newly_applied)
+}
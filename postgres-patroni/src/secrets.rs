@@ -0,0 +1,104 @@
+//! Credential sourcing: file-mounted secrets and etcd-backed secrets
+//!
+//! Patroni's generated YAML still needs each credential as a literal value -
+//! there's no deferred-lookup mechanism in Patroni's own config schema - but
+//! nothing requires sourcing that value from plaintext container env. For
+//! each credential this resolves, in priority order: a mounted file at a
+//! `*_FILE` env var, a DCS-backed value read from etcd (reusing
+//! `PATRONI_ETCD3_HOSTS`), then the literal env var already read by the
+//! caller. Keeps secrets out of the image/env while still landing them in
+//! `/tmp/patroni.yml`, which the caller is expected to lock down to mode
+//! 0600.
+
+use anyhow::{anyhow, Context, Result};
+use common::EtcdClient;
+use tracing::debug;
+
+/// Resolve one credential. `literal` is the value already read from its
+/// plain env var (the existing fallback); `file_env` names a `*_FILE` env
+/// var pointing at a mounted secret file; `etcd_key` is the DCS key to try
+/// when `etcd_hosts` (the comma-separated `PATRONI_ETCD3_HOSTS` value) is
+/// set.
+pub async fn resolve(
+    literal: Option<String>,
+    file_env: &str,
+    etcd_key: &str,
+    etcd_hosts: Option<&str>,
+) -> Result<Option<String>> {
+    if let Ok(path) = std::env::var(file_env) {
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read secret file {} (from {})", path, file_env))?;
+        return Ok(Some(contents.trim().to_string()));
+    }
+
+    if let Some(hosts) = etcd_hosts {
+        if let Some(value) = fetch_from_etcd(hosts, etcd_key).await? {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(literal)
+}
+
+/// Writes a rotated credential back to whichever source `resolve` would read
+/// it from - the mounted `*_FILE` path, or the etcd key - so the next
+/// `patroni_runner` startup re-resolves the *new* value instead of
+/// regenerating `patroni.yml` from a stale one. Returns `false` when neither
+/// a file nor etcd source is configured, meaning the value only lives in the
+/// literal env var baked into the deployment and can't be rewritten here at
+/// all; the caller should treat that as rotation not surviving a restart.
+pub async fn persist(file_env: &str, etcd_key: &str, etcd_hosts: Option<&str>, value: &str) -> Result<bool> {
+    if let Ok(path) = std::env::var(file_env) {
+        std::fs::write(&path, format!("{value}\n"))
+            .with_context(|| format!("failed to write rotated secret back to {} (from {})", path, file_env))?;
+        return Ok(true);
+    }
+
+    if let Some(hosts) = etcd_hosts {
+        let endpoints: Vec<String> = hosts.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+        if !endpoints.is_empty() {
+            let client = EtcdClient::new(endpoints.clone());
+            let endpoint = client.first_healthy(&endpoints).await.ok_or_else(|| {
+                anyhow!("no healthy etcd endpoint reachable to persist rotated secret {}", etcd_key)
+            })?;
+            client.put(&endpoint, etcd_key, value).await?;
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Per-key freshness cache path, so a DCS-backed secret is only logged (and
+/// re-cached) as changed when the value actually differs from the last
+/// successful read, instead of on every restart.
+fn cache_path(etcd_key: &str) -> String {
+    format!("/tmp/.patroni-secret-cache-{}", etcd_key.replace('/', "_"))
+}
+
+async fn fetch_from_etcd(hosts: &str, key: &str) -> Result<Option<String>> {
+    let endpoints: Vec<String> = hosts.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+    if endpoints.is_empty() {
+        return Ok(None);
+    }
+
+    let client = EtcdClient::new(endpoints.clone());
+    let Some(endpoint) = client.first_healthy(&endpoints).await else {
+        return Ok(None);
+    };
+
+    let Some(value) = client.get(&endpoint, key).await? else {
+        return Ok(None);
+    };
+
+    let cache = cache_path(key);
+    let changed = std::fs::read_to_string(&cache)
+        .map(|cached| cached != value)
+        .unwrap_or(true);
+    if changed {
+        debug!(key, "secret value changed, refreshing cache");
+        let _ = std::fs::write(&cache, &value);
+    }
+
+    Ok(Some(value))
+}
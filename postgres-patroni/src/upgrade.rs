@@ -0,0 +1,176 @@
+//! In-place major version upgrade via `pg_upgrade --link`
+//!
+//! A data directory initialized by an older PostgreSQL major than the
+//! installed binaries makes Patroni refuse to start. When enabled, this
+//! detects that case from `{data_dir}/PG_VERSION`, runs `pg_upgrade --link`
+//! into a freshly-initialized data directory while the cluster is stopped,
+//! and leaves the old directory in place as a `.pgN-backup` suffix rather
+//! than deleting it. Only ever run against a node that already has its own
+//! local data (see `patroni_runner`'s `has_pg_control` check) - a replica
+//! with no local data at all never reaches this path and instead re-syncs
+//! from the (now upgraded) leader via the existing `basebackup`
+//! create-replica method once Patroni starts.
+//!
+//! Each installed major version's binaries are assumed to live under their
+//! own versioned directory rather than overwriting each other, matching how
+//! PGDG's own packages lay out `/usr/lib/postgresql/<major>/bin`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use common::{Telemetry, TelemetryEvent};
+use std::path::Path;
+use tokio::fs;
+use tokio::process::Command;
+use tracing::info;
+
+const PG_BIN_ROOT: &str = "/usr/lib/postgresql";
+
+/// If `data_dir` was initialized by an older PostgreSQL major than the
+/// installed binaries, upgrade it in place. A no-op if the directory is
+/// already on the installed major, or if `allow` is false.
+pub async fn maybe_upgrade(data_dir: &str, allow: bool, telemetry: &Telemetry, node: &str) -> Result<()> {
+    if !allow {
+        return Ok(());
+    }
+
+    let Some(data_major) = read_data_dir_major(data_dir).await? else {
+        return Ok(());
+    };
+
+    let target_major = target_major_version().await?;
+    if data_major == target_major {
+        return Ok(());
+    }
+
+    if data_major > target_major {
+        bail!(
+            "data directory is on PostgreSQL {} but installed binaries are {} - downgrades are not supported",
+            data_major, target_major
+        );
+    }
+
+    info!(from = data_major, to = target_major, "Major version upgrade required before starting Patroni");
+    telemetry.send(TelemetryEvent::MajorUpgradeStarted {
+        node: node.to_string(),
+        from_version: data_major.to_string(),
+        to_version: target_major.to_string(),
+    });
+
+    match run_upgrade(data_dir, data_major, target_major).await {
+        Ok(()) => {
+            info!(from = data_major, to = target_major, "Major version upgrade complete");
+            telemetry.send(TelemetryEvent::MajorUpgradeCompleted {
+                node: node.to_string(),
+                from_version: data_major.to_string(),
+                to_version: target_major.to_string(),
+            });
+            Ok(())
+        }
+        Err(e) => {
+            telemetry.send(TelemetryEvent::MajorUpgradeFailed {
+                node: node.to_string(),
+                from_version: data_major.to_string(),
+                to_version: target_major.to_string(),
+                error: e.to_string(),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// Read the data directory's catalog major version from `PG_VERSION`.
+/// Returns `None` if the directory hasn't been initialized yet.
+async fn read_data_dir_major(data_dir: &str) -> Result<Option<u32>> {
+    let path = format!("{}/PG_VERSION", data_dir);
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path).await.context("Failed to read PG_VERSION")?;
+    let major: u32 = contents
+        .trim()
+        .parse()
+        .with_context(|| format!("Unexpected PG_VERSION contents: {:?}", contents))?;
+    Ok(Some(major))
+}
+
+/// Get the installed (target) PostgreSQL major version from `pg_ctl`.
+async fn target_major_version() -> Result<u32> {
+    let output = Command::new("pg_ctl")
+        .arg("--version")
+        .output()
+        .await
+        .context("Failed to run pg_ctl --version")?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    // e.g. "pg_ctl (PostgreSQL) 16.4"
+    let version = text
+        .split_whitespace()
+        .last()
+        .ok_or_else(|| anyhow!("Could not parse pg_ctl --version output: {}", text))?;
+    let major = version.split('.').next().unwrap_or(version);
+    major
+        .parse()
+        .with_context(|| format!("Could not parse major version out of {:?}", version))
+}
+
+/// initdb a fresh data directory on `new_major`, run `pg_upgrade --link`
+/// against it from `old_data_dir`, then move the old directory aside as a
+/// backup and the upgraded directory into `old_data_dir`'s place.
+async fn run_upgrade(old_data_dir: &str, old_major: u32, new_major: u32) -> Result<()> {
+    let old_bin_dir = format!("{}/{}/bin", PG_BIN_ROOT, old_major);
+    if !Path::new(&old_bin_dir).exists() {
+        bail!(
+            "Old PostgreSQL {} binaries not found at {} - cannot upgrade in place",
+            old_major, old_bin_dir
+        );
+    }
+    let new_bin_dir = format!("{}/{}/bin", PG_BIN_ROOT, new_major);
+
+    let staging_dir = format!("{}-pgupgrade-{}", old_data_dir, new_major);
+    if Path::new(&staging_dir).exists() {
+        fs::remove_dir_all(&staging_dir)
+            .await
+            .context("Failed to clean up stale pg_upgrade staging directory")?;
+    }
+
+    info!(staging_dir = %staging_dir, "Initializing new data directory for upgrade target");
+    let status = Command::new(format!("{}/initdb", new_bin_dir))
+        .arg("-D")
+        .arg(&staging_dir)
+        .status()
+        .await
+        .context("Failed to run initdb for upgrade target")?;
+    if !status.success() {
+        bail!("initdb for upgrade target exited with {}", status);
+    }
+
+    info!("Running pg_upgrade --link");
+    let status = Command::new(format!("{}/pg_upgrade", new_bin_dir))
+        .arg("--link")
+        .arg("--old-bindir")
+        .arg(&old_bin_dir)
+        .arg("--new-bindir")
+        .arg(&new_bin_dir)
+        .arg("--old-datadir")
+        .arg(old_data_dir)
+        .arg("--new-datadir")
+        .arg(&staging_dir)
+        .current_dir(&staging_dir)
+        .status()
+        .await
+        .context("Failed to run pg_upgrade")?;
+    if !status.success() {
+        bail!("pg_upgrade exited with {}", status);
+    }
+
+    let backup_dir = format!("{}.pg{}-backup", old_data_dir, old_major);
+    info!(backup_dir = %backup_dir, "Upgrade succeeded, preserving old data directory as backup");
+    fs::rename(old_data_dir, &backup_dir)
+        .await
+        .context("Failed to move old data directory aside")?;
+    fs::rename(&staging_dir, old_data_dir)
+        .await
+        .context("Failed to move upgraded data directory into place")?;
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! End-to-end test for the post-bootstrap binary against a disposable
+//! PostgreSQL instance.
+//!
+//! `PgHarness` starts a throwaway `postgres` process listening only on a
+//! Unix socket under a temp directory, writes a synthetic `patroni.yml`
+//! pointing at known credentials, and runs the compiled `post_bootstrap`
+//! binary against it via `PATRONI_CONFIG_PATH`/`PATRONI_PG_SOCKET_DIR`/
+//! `RAILWAY_VOLUME_MOUNT_PATH`. Everything lives under one `tempfile::TempDir`
+//! so `Drop` tears the server and files down together; no step here
+//! touches the real `/tmp/patroni.yml` or `/var/run/postgresql`.
+
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio_postgres::NoTls;
+
+const SUPERUSER: &str = "postgres";
+const SUPERUSER_PASS: &str = "harness-superuser-pw";
+const REPL_USER: &str = "harness_replicator";
+const REPL_PASS: &str = "harness-repl-pw";
+const APP_USER: &str = "harness_app";
+const APP_PASS: &str = "harness-app-pw";
+const APP_DB: &str = "harness_app_db";
+
+struct PgHarness {
+    _root: TempDir,
+    data_dir: std::path::PathBuf,
+    socket_dir: std::path::PathBuf,
+    volume_root: std::path::PathBuf,
+    patroni_config: std::path::PathBuf,
+    port: u16,
+    server: Child,
+}
+
+impl PgHarness {
+    async fn start() -> anyhow::Result<Self> {
+        let root = TempDir::new()?;
+        let data_dir = root.path().join("pgdata");
+        let socket_dir = root.path().join("sock");
+        let volume_root = root.path().join("volume");
+        std::fs::create_dir_all(&socket_dir)?;
+        std::fs::create_dir_all(&volume_root)?;
+
+        let status = Command::new("initdb")
+            .args(["-D"])
+            .arg(&data_dir)
+            .args(["-U", SUPERUSER, "--auth=trust"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        anyhow::ensure!(status.success(), "initdb failed");
+
+        let port = pick_free_port()?;
+        let server = Command::new("postgres")
+            .arg("-D")
+            .arg(&data_dir)
+            .args(["-k"])
+            .arg(&socket_dir)
+            .args(["-c", "listen_addresses="])
+            .args(["-p", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        wait_for_ready(&socket_dir, port).await?;
+
+        let patroni_config = root.path().join("patroni.yml");
+        std::fs::write(
+            &patroni_config,
+            format!(
+                r#"
+authentication:
+  replication:
+    username: {repl_user}
+    password: {repl_pass}
+  superuser:
+    username: {superuser}
+    password: {superuser_pass}
+app_user:
+  username: {app_user}
+  password: {app_pass}
+  database: {app_db}
+"#,
+                repl_user = REPL_USER,
+                repl_pass = REPL_PASS,
+                superuser = SUPERUSER,
+                superuser_pass = SUPERUSER_PASS,
+                app_user = APP_USER,
+                app_pass = APP_PASS,
+                app_db = APP_DB,
+            ),
+        )?;
+
+        Ok(Self {
+            _root: root,
+            data_dir,
+            socket_dir,
+            volume_root,
+            patroni_config,
+            port,
+            server,
+        })
+    }
+
+    async fn client(&self) -> anyhow::Result<tokio_postgres::Client> {
+        let (client, connection) = tokio_postgres::Config::new()
+            .host_path(&self.socket_dir)
+            .port(self.port)
+            .user(SUPERUSER)
+            .dbname("postgres")
+            .connect(NoTls)
+            .await?;
+        tokio::spawn(connection);
+        Ok(client)
+    }
+
+    fn run_post_bootstrap(&self) -> anyhow::Result<()> {
+        let status = Command::new(env!("CARGO_BIN_EXE_post_bootstrap"))
+            .env("PATRONI_CONFIG_PATH", &self.patroni_config)
+            .env("PATRONI_PG_SOCKET_DIR", &self.socket_dir)
+            .env("RAILWAY_VOLUME_MOUNT_PATH", &self.volume_root)
+            .env("PATRONI_NAME", "harness-node")
+            .env("PGPORT", self.port.to_string())
+            .status()?;
+        anyhow::ensure!(status.success(), "post_bootstrap exited with {status}");
+        Ok(())
+    }
+}
+
+impl Drop for PgHarness {
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+fn pick_free_port() -> anyhow::Result<u16> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+async fn wait_for_ready(socket_dir: &std::path::Path, port: u16) -> anyhow::Result<()> {
+    for _ in 0..50 {
+        let status = Command::new("pg_isready")
+            .args(["-h"])
+            .arg(socket_dir)
+            .args(["-p", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if matches!(status, Ok(s) if s.success()) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    anyhow::bail!("postgres did not become ready in time")
+}
+
+#[tokio::test]
+async fn post_bootstrap_creates_roles_and_database() -> anyhow::Result<()> {
+    let harness = PgHarness::start().await?;
+
+    harness.run_post_bootstrap()?;
+
+    let client = harness.client().await?;
+
+    let repl_row = client
+        .query_one(
+            "SELECT rolreplication FROM pg_roles WHERE rolname = $1",
+            &[&REPL_USER],
+        )
+        .await?;
+    assert!(repl_row.get::<_, bool>(0), "replication role should have rolreplication set");
+
+    let app_role_exists = client
+        .query_opt("SELECT 1 FROM pg_roles WHERE rolname = $1", &[&APP_USER])
+        .await?;
+    assert!(app_role_exists.is_some(), "app role should have been created");
+
+    let app_db_exists = client
+        .query_opt("SELECT 1 FROM pg_database WHERE datname = $1", &[&APP_DB])
+        .await?;
+    assert!(app_db_exists.is_some(), "app database should have been created");
+
+    // The superuser password was rotated during bootstrap - a fresh
+    // connection using the new password should succeed.
+    let (_new_super_client, connection) = tokio_postgres::Config::new()
+        .host_path(&harness.socket_dir)
+        .port(harness.port)
+        .user(SUPERUSER)
+        .password(SUPERUSER_PASS)
+        .dbname("postgres")
+        .connect(NoTls)
+        .await?;
+    tokio::spawn(connection);
+
+    let marker = harness.volume_root.join(".patroni_bootstrap_complete");
+    assert!(marker.exists(), "bootstrap marker file should have been written");
+    assert!(harness.data_dir.join("PG_VERSION").exists(), "data directory should be initialized");
+
+    Ok(())
+}